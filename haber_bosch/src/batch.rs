@@ -0,0 +1,162 @@
+//! Batch execution of independent simulations.
+//!
+//! Every [`HaberBoschInstance`] is simulated on its own, so case studies and sweep points can be
+//! distributed across threads. With the `parallel` cargo feature the work is done by rayon,
+//! without it the items are processed one after another. The results always come back in the
+//! order of the input, so the output does not depend on the number of threads.
+//!
+//! The `parallel` feature is meant for the native binaries, the WASM build has no threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::configuration::HaberBoschInstance;
+use crate::simulation::{sequential_simulation, SimulationError};
+
+/// Reported each time an item of the batch is finished.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// number of finished items, including the one this report is about
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Runs a batch of independent jobs, in parallel if the `parallel` feature is enabled.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BatchRunner {
+    /// number of worker threads, rayon picks one per core if None
+    threads: Option<usize>,
+}
+
+impl BatchRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of worker threads, ignored without the `parallel` feature.
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads.filter(|n| *n > 0);
+        self
+    }
+
+    /// True if the jobs are distributed across threads.
+    pub fn is_parallel(&self) -> bool {
+        cfg!(feature = "parallel") && self.threads != Some(1)
+    }
+
+    /// Applies `job` to every item, the results are in the order of `items`.
+    ///
+    /// `progress` is called from the worker threads after each finished item.
+    pub fn map<T, R, F, P>(&self, items: Vec<T>, job: F, progress: P) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+        P: Fn(BatchProgress) + Sync,
+    {
+        let total = items.len();
+        let done = AtomicUsize::new(0);
+        let run = |item: T| {
+            let res = job(item);
+            progress(BatchProgress {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+            });
+            res
+        };
+
+        self.execute(items, run)
+    }
+
+    /// Simulates all instances, the failed ones are returned with their error.
+    pub fn simulate<P>(
+        &self,
+        instances: Vec<HaberBoschInstance>,
+        progress: P,
+    ) -> Vec<Result<HaberBoschInstance, SimulationError>>
+    where
+        P: Fn(BatchProgress) + Sync,
+    {
+        self.map(
+            instances,
+            |mut inst| sequential_simulation(&mut inst, false).map(|_| inst),
+            progress,
+        )
+    }
+
+    #[cfg(feature = "parallel")]
+    fn execute<T: Send, R: Send>(&self, items: Vec<T>, run: impl Fn(T) -> R + Sync) -> Vec<R> {
+        use rayon::prelude::*;
+
+        let par_run = || items.into_par_iter().map(&run).collect();
+        match self.threads {
+            // a pool of its own, the global one may already be sized differently
+            Some(threads) => match rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+            {
+                Ok(pool) => pool.install(par_run),
+                Err(_) => par_run(),
+            },
+            None => par_run(),
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn execute<T, R>(&self, items: Vec<T>, run: impl Fn(T) -> R) -> Vec<R> {
+        items.into_iter().map(run).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::configuration::{Catalyst, HaberBoschInstanceBuilder};
+    use crate::v2_hints::*;
+
+    #[test]
+    fn keeps_the_order_of_the_items() {
+        // the first items take longest, so they finish last on several threads
+        let items: Vec<u64> = (0..16).collect();
+        let reports = Mutex::new(vec![]);
+        for threads in [Some(1), Some(4), None] {
+            reports.lock().unwrap().clear();
+            let res = BatchRunner::new().with_threads(threads).map(
+                items.clone(),
+                |item| {
+                    std::thread::sleep(Duration::from_millis(16 - item));
+                    item * 10
+                },
+                |p| reports.lock().unwrap().push(p),
+            );
+            assert_eq!(res, items.iter().map(|item| item * 10).collect::<Vec<_>>());
+
+            let mut done: Vec<_> = reports.lock().unwrap().iter().map(|p| p.done).collect();
+            done.sort();
+            assert_eq!(done, (1..=items.len()).collect::<Vec<_>>());
+            assert!(reports.lock().unwrap().iter().all(|p| p.total == items.len()));
+        }
+    }
+
+    #[test]
+    fn simulates_like_one_by_one() {
+        let inst = |t_start: f64| {
+            HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+                .add_bed(t_start, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+                .build()
+                .unwrap()
+        };
+        let instances: Vec<_> = [KMIR_B1_TS, KMIR_B1_TS + 20., KMIR_B1_TS + 40.]
+            .map(inst)
+            .to_vec();
+
+        let results = BatchRunner::new().simulate(instances.clone(), |_| {});
+        for (mut inst, res) in instances.into_iter().zip(results) {
+            sequential_simulation(&mut inst, false).unwrap();
+            let yields = [inst.summary(), res.unwrap().summary()].map(|s| s.unwrap().final_yield);
+            assert_eq!(yields[0], yields[1]);
+        }
+    }
+}
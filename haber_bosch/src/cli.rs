@@ -0,0 +1,765 @@
+//! Command line interface of `hb_seq_simulation`.
+//!
+//! Without a subcommand the two case studies of the video series are simulated and plotted, like
+//! the binary always did.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::batch::BatchRunner;
+use crate::config::{CatalystConfig, ConfigError, ReactorConfig};
+use crate::configuration::{
+    Catalyst, CatalystKinetics, HaberBoschInstance, HaberBoschInstanceBuilder,
+};
+use crate::export::{CompositionUnit, ExportError, ExportOptions, TemperatureUnit};
+use crate::optimization::{Objective, OptimizationError, OptimizationProblem};
+use crate::recycle::{recycle_simulation, RecycleError, RecycleSettings};
+use crate::simulation::{sequential_simulation, SimulationError};
+use crate::sweep::{Sampling, Sweep, SweepDimension, SweepParameter};
+use crate::v2_hints::*;
+use crate::visualization::{self, CurveOverlay, PlotSettings, PlotStyle, Scenario};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "hb_seq_simulation",
+    version,
+    about = "Sequential simulation of Haber-Bosch reactor beds"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// More output, -v prints the configurations, -vv the solver input of each bed
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Number of threads for the simulations, one per core if not given, needs the `parallel`
+    /// feature
+    #[arg(short, long, global = true)]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Simulates the case studies, prints their summaries and plots them
+    Simulate {
+        /// Configuration files (.toml or .json), the built-in case studies if none are given
+        configs: Vec<PathBuf>,
+
+        /// Do not write any plots
+        #[arg(long)]
+        no_plots: bool,
+
+        /// Also solve the recycle loop around the converter
+        #[arg(long)]
+        recycle: bool,
+
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+    /// Varies one parameter of a case study and prints the resulting yields
+    Sweep(SweepArgs),
+    /// Only writes the plots of the case studies
+    Plot {
+        /// Configuration files (.toml or .json), the built-in case studies if none are given
+        configs: Vec<PathBuf>,
+    },
+    /// Simulates case studies, prints their key figures and plots them on top of each other
+    Compare {
+        /// Configuration files (.toml or .json), the built-in case studies if none are given
+        configs: Vec<PathBuf>,
+
+        /// Do not write the comparison plots
+        #[arg(long)]
+        no_plots: bool,
+    },
+    /// Searches the bed temperatures with the best yield
+    Optimize(OptimizeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Directory the plots are written to
+    #[arg(short, long, default_value = ".", global = true)]
+    pub output_dir: PathBuf,
+
+    /// Width of the plots in pixels
+    #[arg(long, default_value_t = 1920, global = true)]
+    pub width: u32,
+
+    /// Height of the plots in pixels
+    #[arg(long, default_value_t = 1080, global = true)]
+    pub height: u32,
+
+    /// Pixels per inch, sets the page size of PDF plots
+    #[arg(long, default_value_t = 96, global = true)]
+    pub dpi: u32,
+
+    /// Image format of the plots
+    #[arg(short, long, value_enum, default_value_t = ImageFormat::Png, global = true)]
+    pub format: ImageFormat,
+
+    /// Leave the equilibrium curve out of the temperature over yield plot
+    #[arg(long, global = true)]
+    pub no_equilibrium: bool,
+
+    /// Add the maximum rate curve to the temperature over yield plot
+    #[arg(long, global = true)]
+    pub max_rate: bool,
+}
+
+impl OutputArgs {
+    pub fn plot_settings(&self) -> PlotSettings {
+        PlotSettings::new((self.width, self.height)).with_dpi(self.dpi)
+    }
+
+    pub fn overlay(&self) -> CurveOverlay {
+        CurveOverlay {
+            equilibrium: !self.no_equilibrium,
+            max_rate: self.max_rate,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Write the axial profiles or the rate terms in the given formats to the output directory
+    #[arg(short, long, value_enum)]
+    pub export: Vec<ExportFormat>,
+
+    /// Composition in the profiles: fraction or partial-pressure
+    #[arg(long, default_value = "fraction")]
+    pub composition: CompositionUnit,
+
+    /// Temperature unit in the profiles: celsius or kelvin
+    #[arg(long, default_value = "celsius")]
+    pub temperature_unit: TemperatureUnit,
+
+    /// Print the summary as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    /// needs the `parquet` feature
+    Parquet,
+    /// the reaction rate terms along the beds as CSV
+    Rates,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormat {
+    Png,
+    Jpg,
+    Bmp,
+    Svg,
+    /// requires the `pdf` feature
+    Pdf,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Pdf => "pdf",
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SweepArgs {
+    /// Configuration file of the case study, the built-in KMIR study if not given
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// The parameter that is varied: pressure, t-start, t-slope, t-max, beta or beds
+    #[arg(short, long, requires_all = ["min", "max"])]
+    pub parameter: Option<SweepParameter>,
+
+    /// The reactor bed (starting at 1) for bed parameters
+    #[arg(short, long, default_value_t = 1)]
+    pub bed: usize,
+
+    /// First value, temperatures are given in Kelvin
+    #[arg(long, allow_negative_numbers = true)]
+    pub min: Option<f64>,
+
+    /// Last value, temperatures are given in Kelvin
+    #[arg(long, allow_negative_numbers = true)]
+    pub max: Option<f64>,
+
+    /// Number of values between min and max
+    #[arg(long, default_value_t = 11)]
+    pub steps: usize,
+
+    /// Further dimensions as parameter[@bed]=min:max[:steps], e.g. t-max@2=700:760:4
+    #[arg(short, long)]
+    pub dimension: Vec<SweepDimension>,
+
+    /// Simulate every point with each of these catalysts
+    #[arg(long)]
+    pub catalyst: Vec<String>,
+
+    /// Draw this many points by Latin hypercube sampling instead of the Cartesian product
+    #[arg(long)]
+    pub samples: Option<usize>,
+
+    /// Seed of the Latin hypercube sampling
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Also write the table as CSV to the output directory
+    #[arg(long)]
+    pub csv: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct OptimizeArgs {
+    /// Configuration file of the start point, otherwise the beds are generated for the catalyst
+    #[arg(short, long, conflicts_with_all = ["catalyst", "pressure", "beds"])]
+    pub config: Option<PathBuf>,
+
+    /// Catalyst of the generated beds
+    #[arg(long, default_value = "KMIR")]
+    pub catalyst: CatalystKinetics,
+
+    /// Pressure in bar of the generated beds
+    #[arg(long, default_value_t = KMIR_REACTOR_PRESSURE)]
+    pub pressure: f64,
+
+    /// Number of generated beds
+    #[arg(long, default_value_t = 2)]
+    pub beds: usize,
+
+    /// What is maximized: yield or yield-per-length
+    #[arg(long, default_value = "yield")]
+    pub objective: Objective,
+
+    /// No bed gets hotter than this temperature in Kelvin
+    #[arg(long)]
+    pub max_temperature: Option<f64>,
+
+    /// Longer reactors are penalized
+    #[arg(long)]
+    pub max_length: Option<f64>,
+
+    /// Only vary t_start of the beds
+    #[arg(long)]
+    pub fixed_t_max: bool,
+
+    #[arg(long, default_value_t = OPTIMIZER_MAX_EVALUATIONS)]
+    pub max_evaluations: usize,
+
+    /// Save the optimized configuration, TOML or JSON by extension
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+
+    /// Write the search history as CSV to the output directory
+    #[arg(long)]
+    pub history: bool,
+}
+
+/// Errors of the command line interface, each kind has its own exit code.
+#[derive(Debug)]
+pub enum CliError {
+    /// A configuration file or argument is invalid.
+    Config(ConfigError),
+    /// The simulation of the named case study failed.
+    Simulation {
+        study: String,
+        error: SimulationError,
+    },
+    /// The recycle loop of the named case study did not converge.
+    Recycle { study: String, error: RecycleError },
+    /// The optimization did not find a point that can be simulated.
+    Optimization(OptimizationError),
+    /// A plot could not be written.
+    Output(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Config(_) => ExitCode::from(2),
+            CliError::Simulation { .. }
+            | CliError::Recycle { .. }
+            | CliError::Optimization(_) => ExitCode::from(3),
+            CliError::Output(_) => ExitCode::from(4),
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Config(e) => write!(f, "{}", e),
+            CliError::Simulation { study, error } => {
+                write!(f, "Simulation of {} failed: {}", study, error)
+            }
+            CliError::Recycle { study, error } => {
+                write!(f, "Recycle loop of {} failed: {}", study, error)
+            }
+            CliError::Optimization(e) => write!(f, "Optimization failed: {}", e),
+            CliError::Output(msg) => write!(f, "Cannot write output: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<ConfigError> for CliError {
+    fn from(e: ConfigError) -> Self {
+        CliError::Config(e)
+    }
+}
+
+impl From<OptimizationError> for CliError {
+    fn from(e: OptimizationError) -> Self {
+        match e {
+            OptimizationError::Config(e) => CliError::Config(e),
+            e => CliError::Optimization(e),
+        }
+    }
+}
+
+/// Runs the command line interface, the errors are already reported when this returns.
+pub fn run(cli: Cli) -> ExitCode {
+    match dispatch(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn dispatch(cli: &Cli) -> Result<(), CliError> {
+    match &cli.command {
+        // the case studies of the video series together with their recycle loops
+        None => simulate(cli, &[], true, true, None),
+        Some(Command::Simulate {
+            configs,
+            no_plots,
+            recycle,
+            export,
+        }) => simulate(cli, configs, !no_plots, *recycle, Some(export)),
+        Some(Command::Plot { configs }) => {
+            let studies = load_studies(configs)?;
+            for (study, inst) in studies.iter().zip(run_studies(cli, &studies)?) {
+                let name = study_name(study);
+                write_plots(cli, &name, &[Scenario::new(&inst, name.clone())])?;
+            }
+            Ok(())
+        }
+        Some(Command::Sweep(args)) => sweep(cli, args),
+        Some(Command::Compare { configs, no_plots }) => compare(cli, configs, !no_plots),
+        Some(Command::Optimize(args)) => optimize(cli, args),
+    }
+}
+
+fn simulate(
+    cli: &Cli,
+    configs: &[PathBuf],
+    plots: bool,
+    recycle: bool,
+    export: Option<&ExportArgs>,
+) -> Result<(), CliError> {
+    let studies = load_studies(configs)?;
+    for (study, inst) in studies.iter().zip(run_studies(cli, &studies)?) {
+        let name = study_name(study);
+        if !cli.quiet {
+            if let Some(summary) = inst.summary() {
+                if export.is_some_and(|e| e.json) {
+                    let json = serde_json::to_string_pretty(&summary)
+                        .map_err(|e| CliError::Output(e.to_string()))?;
+                    println!("{}", json);
+                } else {
+                    println!("{}", summary);
+                }
+            }
+        }
+
+        if recycle {
+            let result =
+                recycle_simulation(&inst, &RecycleSettings::default()).map_err(|error| {
+                    CliError::Recycle {
+                        study: name.clone(),
+                        error,
+                    }
+                })?;
+            if !cli.quiet {
+                println!("{}\n", result);
+            }
+        }
+
+        if plots {
+            write_plots(cli, &name, &[Scenario::new(&inst, name.clone())])?;
+        }
+        if let Some(export) = export {
+            write_profiles(cli, export, &name, &inst)?;
+        }
+    }
+    Ok(())
+}
+
+fn sweep(cli: &Cli, args: &SweepArgs) -> Result<(), CliError> {
+    let base = match &args.config {
+        Some(path) => ReactorConfig::load(path)?,
+        None => builtin_studies()?.remove(0),
+    };
+    let name = study_name(&base);
+
+    let mut sweep = Sweep::new(base);
+    if let (Some(parameter), Some(min), Some(max)) = (args.parameter, args.min, args.max) {
+        sweep = sweep.with_dimension(SweepDimension::new(
+            parameter, args.bed, min, max, args.steps,
+        ));
+    }
+    for dim in &args.dimension {
+        sweep = sweep.with_dimension(*dim);
+    }
+    for catalyst in &args.catalyst {
+        sweep = sweep.with_catalyst(CatalystConfig::Name(catalyst.clone()));
+    }
+    if let Some(samples) = args.samples {
+        sweep = sweep.with_sampling(Sampling::LatinHypercube {
+            samples,
+            seed: args.seed,
+        });
+    }
+    if sweep.dimensions.is_empty() && sweep.catalysts.is_empty() {
+        return Err(CliError::Config(ConfigError::Invalid {
+            field: "parameter".to_owned(),
+            message: "nothing to sweep, give a parameter, a dimension or catalysts".to_owned(),
+        }));
+    }
+
+    // an invalid point of the sweep is reported in the table but does not stop it
+    let table = sweep.run_with(&runner(cli), |p| {
+        if !cli.quiet {
+            eprint!("\rSimulated {}/{} points", p.done, p.total);
+        }
+    })?;
+    if !cli.quiet {
+        eprintln!();
+        print!("{}", table);
+    }
+
+    if args.csv {
+        create_output_dir(cli)?;
+        let path = cli.output.output_dir.join(format!("{}_sweep.csv", name));
+        table
+            .export_csv(&path)
+            .map_err(|e| CliError::Output(format!("{}: {}", path.display(), e)))?;
+        if !cli.quiet {
+            println!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn optimize(cli: &Cli, args: &OptimizeArgs) -> Result<(), CliError> {
+    let problem = match &args.config {
+        Some(path) => OptimizationProblem::new(ReactorConfig::load(path)?),
+        None => {
+            OptimizationProblem::for_catalyst(args.catalyst.clone(), args.pressure, args.beds)?
+        }
+    };
+    let name = study_name(&problem.base);
+    let problem = problem
+        .with_objective(args.objective)
+        .with_max_temperature(args.max_temperature.unwrap_or(f64::INFINITY))
+        .with_max_total_length(args.max_length)
+        .with_optimize_t_max(!args.fixed_t_max)
+        .with_max_evaluations(args.max_evaluations);
+
+    let result = problem.optimize()?;
+    if !cli.quiet {
+        println!("{}", result);
+        if let Some(summary) = result.instance.summary() {
+            println!("{}", summary);
+        }
+    }
+
+    if let Some(path) = &args.save {
+        let config = ReactorConfig {
+            name: Some(format!("{}_optimized", name)),
+            ..result.config.clone()
+        };
+        config.save(path)?;
+        if !cli.quiet {
+            println!("Wrote {}", path.display());
+        }
+    }
+    if args.history {
+        create_output_dir(cli)?;
+        let path = cli.output.output_dir.join(format!("{}_history.csv", name));
+        result
+            .export_history_csv(&path)
+            .map_err(|e| CliError::Output(format!("{}: {}", path.display(), e)))?;
+        if !cli.quiet {
+            println!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn compare(cli: &Cli, configs: &[PathBuf], plots: bool) -> Result<(), CliError> {
+    let studies = load_studies(configs)?;
+    let instances = run_studies(cli, &studies)?;
+    let study_names: Vec<_> = studies.iter().map(study_name).collect();
+    // variants of one study usually keep its name, the file tells them apart
+    let names: Vec<_> = study_names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let duplicate = study_names.iter().filter(|other| *other == name).count() > 1;
+            match configs.get(idx).and_then(|path| path.file_stem()) {
+                Some(stem) if duplicate => format!("{} ({})", name, stem.to_string_lossy()),
+                _ => name.clone(),
+            }
+        })
+        .collect();
+
+    if !cli.quiet {
+        print_comparison(&names, &instances);
+    }
+
+    if plots {
+        let scenarios: Vec<_> = names
+            .iter()
+            .zip(&instances)
+            .map(|(name, inst)| Scenario::new(inst, name.clone()))
+            .collect();
+        write_plots(cli, "compare", &scenarios)?;
+        for (idx, other) in scenarios.iter().enumerate().skip(1) {
+            write_difference(cli, &format!("compare_diff_{}", idx + 1), &scenarios[0], other)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the key figures of the studies, the deltas are relative to the first study.
+fn print_comparison(names: &[String], instances: &[HaberBoschInstance]) {
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "{:<width$} {:>10} {:>10} {:>11} {:>12} {:>10} {:>10} {:>11}",
+        "", "Length", "Yield", "T_out [°C]", "T_peak [°C]", "ΔYield", "ΔLength", "ΔT_peak"
+    );
+    for (idx, (name, inst)) in names.iter().zip(instances).enumerate() {
+        let (length, yield_nh3, t_out) = key_figures(inst);
+        let t_peak = inst.summary().map_or(f64::NAN, |s| s.peak_temperature);
+        print!(
+            "{:<width$} {:>10.3} {:>10.4} {:>11.1} {:>12.1}",
+            name, length, yield_nh3, t_out, t_peak
+        );
+        if let Some(deltas) = inst.metric_deltas(&instances[0]).filter(|_| idx > 0) {
+            let [d_yield, d_length, d_peak] = [0, 1, 2].map(|k| deltas[k].delta);
+            print!(" {:>+10.4} {:>+10.3} {:>+11.1}", d_yield, d_length, d_peak);
+        }
+        println!();
+    }
+}
+
+/// The built-in case studies of the video series.
+fn builtin_studies() -> Result<Vec<ReactorConfig>, CliError> {
+    let kmir = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+        .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+        .add_bed(KMIR_B2_TS, KMIR_B2_TR, KMIR_B2_TM, KMIR_B2_B)
+        .build()
+        .map_err(ConfigError::from)?;
+    let fn_cat = HaberBoschInstanceBuilder::create(FN_REACTOR_PRESSURE, Catalyst::FN)
+        .add_bed(FN_B1_TS, FN_B1_TR, FN_B1_TM, FN_B1_B)
+        .add_bed(FN_B2_TS, FN_B2_TR, FN_B2_TM, FN_B2_B)
+        .build()
+        .map_err(ConfigError::from)?;
+
+    Ok([(kmir, "HB_KMIR"), (fn_cat, "HB_FN")]
+        .into_iter()
+        .map(|(inst, name)| ReactorConfig {
+            name: Some(name.to_owned()),
+            ..ReactorConfig::from_instance(&inst)
+        })
+        .collect())
+}
+
+fn load_studies(configs: &[PathBuf]) -> Result<Vec<ReactorConfig>, CliError> {
+    if configs.is_empty() {
+        return builtin_studies();
+    }
+
+    configs
+        .iter()
+        .map(|path| {
+            let mut study = ReactorConfig::load(path)?;
+            if study.name.is_none() {
+                study.name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned());
+            }
+            Ok(study)
+        })
+        .collect()
+}
+
+fn study_name(study: &ReactorConfig) -> String {
+    study.name.clone().unwrap_or_else(|| "HB".to_owned())
+}
+
+fn run_study(cli: &Cli, study: &ReactorConfig) -> Result<HaberBoschInstance, CliError> {
+    let mut inst = study.build()?;
+    if cli.verbose >= 1 {
+        println!("{:?}", inst);
+    }
+
+    sequential_simulation(&mut inst, cli.verbose >= 2).map_err(|error| CliError::Simulation {
+        study: study_name(study),
+        error,
+    })?;
+    Ok(inst)
+}
+
+fn runner(cli: &Cli) -> BatchRunner {
+    BatchRunner::new().with_threads(cli.jobs)
+}
+
+/// Simulates the studies on the threads of the batch runner, the first error in the order of
+/// `studies` is returned.
+fn run_studies(cli: &Cli, studies: &[ReactorConfig]) -> Result<Vec<HaberBoschInstance>, CliError> {
+    runner(cli)
+        .map(studies.iter().collect(), |study| run_study(cli, study), |_| {})
+        .into_iter()
+        .collect()
+}
+
+/// Length, ammonia yield and temperature [°C] at the outlet of the last bed.
+fn key_figures(inst: &HaberBoschInstance) -> (f32, f32, f32) {
+    let (length, yield_nh3) = inst.iter_my(2, true).last().unwrap_or_default();
+    let (_, t_out) = inst.iter_my(5, false).last().unwrap_or_default();
+    (length, yield_nh3, t_out)
+}
+
+fn write_plots(cli: &Cli, name: &str, scenarios: &[Scenario]) -> Result<(), CliError> {
+    let out = &cli.output;
+    create_output_dir(cli)?;
+
+    let settings = out.plot_settings();
+    let style = PlotStyle::new().with_overlay(out.overlay());
+    let ext = out.format.extension();
+    let fn_conc = out.output_dir.join(format!("{}_conc.{}", name, ext));
+    let fn_temp_over_yield = out.output_dir.join(format!("{}_temp_yield.{}", name, ext));
+
+    visualization::draw_concentations(&fn_conc.to_string_lossy(), scenarios, &settings, &style)
+        .map_err(|e| CliError::Output(format!("{}: {}", fn_conc.display(), e)))?;
+    visualization::draw_temperature_over_yield(
+        &fn_temp_over_yield.to_string_lossy(),
+        scenarios,
+        &settings,
+        &style,
+    )
+    .map_err(|e| CliError::Output(format!("{}: {}", fn_temp_over_yield.display(), e)))?;
+
+    if !cli.quiet {
+        println!(
+            "Wrote {} and {}",
+            fn_conc.display(),
+            fn_temp_over_yield.display()
+        );
+    }
+    Ok(())
+}
+
+fn write_difference(
+    cli: &Cli,
+    name: &str,
+    base: &Scenario,
+    other: &Scenario,
+) -> Result<(), CliError> {
+    let out = &cli.output;
+    let style = PlotStyle::new().with_overlay(out.overlay());
+    let filename = out
+        .output_dir
+        .join(format!("{}.{}", name, out.format.extension()));
+
+    visualization::draw_difference(
+        &filename.to_string_lossy(),
+        base,
+        other,
+        &out.plot_settings(),
+        &style,
+    )
+    .map_err(|e| CliError::Output(format!("{}: {}", filename.display(), e)))?;
+
+    if !cli.quiet {
+        println!("Wrote {}", filename.display());
+    }
+    Ok(())
+}
+
+fn write_profiles(
+    cli: &Cli,
+    args: &ExportArgs,
+    name: &str,
+    inst: &HaberBoschInstance,
+) -> Result<(), CliError> {
+    let options = ExportOptions {
+        composition: args.composition,
+        temperature: args.temperature_unit,
+    };
+
+    if !args.export.is_empty() {
+        create_output_dir(cli)?;
+    }
+
+    for format in &args.export {
+        let file_name = match format {
+            ExportFormat::Csv => format!("{}_profile.csv", name),
+            ExportFormat::Parquet => format!("{}_profile.parquet", name),
+            ExportFormat::Rates => format!("{}_rates.csv", name),
+        };
+        let path = cli.output.output_dir.join(file_name);
+        let res: Result<(), ExportError> = match format {
+            ExportFormat::Csv => inst.export_csv(&path, &options),
+            ExportFormat::Rates => inst.export_rate_diagnostics_csv(&path),
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => inst.export_parquet(&path, &options),
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => Err(ExportError::Parquet(
+                "built without the parquet feature".to_owned(),
+            )),
+        };
+        res.map_err(|e| CliError::Output(format!("{}: {}", path.display(), e)))?;
+
+        if !cli.quiet {
+            println!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn create_output_dir(cli: &Cli) -> Result<(), CliError> {
+    let dir = &cli.output.output_dir;
+    std::fs::create_dir_all(dir).map_err(|e| CliError::Output(format!("{}: {}", dir.display(), e)))
+}
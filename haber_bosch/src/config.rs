@@ -0,0 +1,420 @@
+//! Reactor configurations as data files.
+//!
+//! A [`ReactorConfig`] describes everything `HaberBoschInstanceBuilder` needs: pressure, catalyst,
+//! feed, solver settings and the reactor beds. It can be stored as TOML or JSON, the format is
+//! chosen by the file extension. Temperatures are given in Kelvin like in `v2_hints.rs`.
+//!
+//! Errors while loading point at the offending field, e.g. `beds[1].t_start`, so a broken case
+//! study can be fixed without reading the source code.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::{
+    Catalyst, CatalystKinetics, ConfigurationError, FeedComposition, HaberBoschBedSetup,
+    HaberBoschInstance, HaberBoschInstanceBuilder, SolverSettings,
+};
+use crate::v2_hints::*;
+
+/// Supported file formats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Picks the format by the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        ConfigFormat::from_str(ext)
+            .map_err(|_| ConfigError::UnknownFormat(path.display().to_string()))
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            _ => Err("Unknown configuration format".to_owned()),
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigFormat::Toml => write!(f, "TOML"),
+            ConfigFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Errors of loading or saving a reactor configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read or written.
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The extension of the file is neither `.toml` nor `.json`.
+    UnknownFormat(String),
+    /// The file is no valid TOML or JSON or does not match the schema at `field`.
+    Parse {
+        format: ConfigFormat,
+        field: String,
+        message: String,
+    },
+    /// The value at `field` is well-formed but cannot be used.
+    Invalid { field: String, message: String },
+    /// The configuration could not be written in the given format.
+    Serialize {
+        format: ConfigFormat,
+        message: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "Cannot access {}: {}", path, source),
+            ConfigError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "Unknown configuration format of {}, use .toml or .json",
+                    path
+                )
+            }
+            ConfigError::Parse {
+                format,
+                field,
+                message,
+            } => {
+                write!(f, "Invalid {} at '{}': {}", format, field, message)
+            }
+            ConfigError::Invalid { field, message } => {
+                write!(f, "Invalid value of '{}': {}", field, message)
+            }
+            ConfigError::Serialize { format, message } => {
+                write!(f, "Cannot write {}: {}", format, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigurationError> for ConfigError {
+    fn from(e: ConfigurationError) -> Self {
+        let field = match &e {
+            ConfigurationError::InvalidMoleFraction { .. }
+            | ConfigurationError::MoleFractionSum { .. } => "feed".to_owned(),
+            ConfigurationError::InvalidCatalyst { .. } => "catalyst".to_owned(),
+            ConfigurationError::NoBeds => "beds".to_owned(),
+            ConfigurationError::OutOfRange { field, .. } => field.clone(),
+        };
+        ConfigError::Invalid {
+            field,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// The catalyst is either given by the name of a built-in or registered catalyst or by its
+/// kinetic parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CatalystConfig {
+    Name(String),
+    Kinetics(CatalystKinetics),
+}
+
+/// Mole fractions of the feed, checked with `FeedComposition::new` while loading.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedConfig {
+    pub n2: f64,
+    pub h2: f64,
+    pub nh3: f64,
+    pub ar: f64,
+    pub ch4: f64,
+}
+
+impl From<FeedComposition> for FeedConfig {
+    fn from(feed: FeedComposition) -> Self {
+        let [n2, h2, nh3, ar, ch4] = feed.as_array();
+        FeedConfig {
+            n2,
+            h2,
+            nh3,
+            ar,
+            ch4,
+        }
+    }
+}
+
+fn default_feed_flow() -> f64 {
+    FEED_MOLAR_FLOW
+}
+
+/// A complete reactor configuration as it is stored in TOML or JSON files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReactorConfig {
+    /// name of the case study, used as prefix of the output files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// [bar] pressure at the inlet of the first bed
+    pub pressure: f64,
+    pub catalyst: CatalystConfig,
+    /// the feed of the video series if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed: Option<FeedConfig>,
+    /// [mol/s] molar flow entering the first bed
+    #[serde(default = "default_feed_flow")]
+    pub feed_flow: f64,
+    #[serde(default)]
+    pub solver: SolverSettings,
+    pub beds: Vec<HaberBoschBedSetup>,
+}
+
+impl ReactorConfig {
+    /// Describes the configuration of `inst`, the results are not part of it.
+    pub fn from_instance(inst: &HaberBoschInstance) -> Self {
+        // registered catalysts are only known to the running process, files keep their kinetics
+        let catalyst = match Catalyst::from_str(&inst.cat().name).map(|cat| cat.kinetics()) {
+            Ok(kin) if kin == *inst.cat() => CatalystConfig::Name(kin.name),
+            _ => CatalystConfig::Kinetics(inst.cat().clone()),
+        };
+
+        ReactorConfig {
+            name: None,
+            pressure: inst.pres(),
+            catalyst,
+            feed: Some(inst.feed().into()),
+            feed_flow: inst.feed_flow(),
+            solver: inst.solver_settings(),
+            beds: inst.beds().to_vec(),
+        }
+    }
+
+    /// Loads a configuration, the format is given by the extension of `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::parse(&content, format)
+    }
+
+    /// Parses and validates a configuration.
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        let conf: ReactorConfig = match format {
+            ConfigFormat::Toml => {
+                let de = toml::Deserializer::new(content);
+                serde_path_to_error::deserialize(de).map_err(|e| ConfigError::Parse {
+                    format,
+                    field: e.path().to_string(),
+                    message: e.into_inner().message().to_owned(),
+                })?
+            }
+            ConfigFormat::Json => {
+                let mut de = serde_json::Deserializer::from_str(content);
+                serde_path_to_error::deserialize(&mut de).map_err(|e| ConfigError::Parse {
+                    format,
+                    field: e.path().to_string(),
+                    message: e.into_inner().to_string(),
+                })?
+            }
+        };
+
+        // the builder checks the values that are well-formed but physically meaningless
+        conf.build()?;
+        Ok(conf)
+    }
+
+    /// Writes the configuration, the format is given by the extension of `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let content = self.to_string(ConfigFormat::from_path(path)?)?;
+        std::fs::write(path, content).map_err(|source| ConfigError::Io {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String, ConfigError> {
+        match format {
+            ConfigFormat::Toml => {
+                toml::to_string_pretty(self).map_err(|e| ConfigError::Serialize {
+                    format,
+                    message: e.to_string(),
+                })
+            }
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|e| ConfigError::Serialize {
+                    format,
+                    message: e.to_string(),
+                })
+            }
+        }
+    }
+
+    pub fn catalyst_kinetics(&self) -> Result<CatalystKinetics, ConfigError> {
+        let kin = match &self.catalyst {
+            CatalystConfig::Name(name) => CatalystKinetics::from_str(name),
+            CatalystConfig::Kinetics(kin) => kin
+                .validate()
+                .map(|_| kin.clone())
+                .map_err(|e| e.to_string()),
+        };
+        kin.map_err(|message| ConfigError::Invalid {
+            field: "catalyst".to_owned(),
+            message,
+        })
+    }
+
+    pub fn feed_composition(&self) -> Result<FeedComposition, ConfigError> {
+        let Some(feed) = self.feed else {
+            return Ok(FeedComposition::default());
+        };
+        FeedComposition::new(feed.n2, feed.h2, feed.nh3, feed.ar, feed.ch4).map_err(|e| {
+            ConfigError::Invalid {
+                field: "feed".to_owned(),
+                message: e.to_string(),
+            }
+        })
+    }
+
+    /// Creates the instance described by the configuration.
+    pub fn build(&self) -> Result<HaberBoschInstance, ConfigError> {
+        let mut builder =
+            HaberBoschInstanceBuilder::create(self.pressure, self.catalyst_kinetics()?)
+                .with_solver_settings(self.solver)
+                .with_feed_composition(self.feed_composition()?)
+                .with_feed_flow(self.feed_flow);
+        for bed in &self.beds {
+            builder = builder.add_bed_setup(*bed);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::is_positive;
+
+    fn case_studies() -> Vec<ReactorConfig> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("configs");
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| ReactorConfig::load(path).unwrap()).collect()
+    }
+
+    #[test]
+    fn round_trips_through_both_formats() {
+        for conf in case_studies() {
+            for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+                let content = conf.to_string(format).unwrap();
+                let parsed = ReactorConfig::parse(&content, format).unwrap();
+                assert_eq!(parsed.to_string(format).unwrap(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn saves_and_loads_files() {
+        let dir = std::env::temp_dir().join(format!("hb_config_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (idx, conf) in case_studies().iter().enumerate() {
+            for ext in ["toml", "json"] {
+                let path = dir.join(format!("case_{}.{}", idx, ext));
+                conf.save(&path).unwrap();
+                let loaded = ReactorConfig::load(&path).unwrap();
+
+                assert_eq!(loaded.name, conf.name);
+                assert_eq!(loaded.catalyst, conf.catalyst);
+                assert_eq!(loaded.beds.len(), conf.beds.len());
+                let format = ConfigFormat::from_path(&path).unwrap();
+                assert_eq!(loaded.to_string(format).unwrap(), conf.to_string(format).unwrap());
+            }
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn round_trips_an_instance() {
+        let conf = &case_studies()[0];
+        let inst = conf.build().unwrap();
+        let again = ReactorConfig::from_instance(&inst);
+        assert_eq!(again.build().unwrap().beds().len(), inst.beds().len());
+        assert_eq!(again.pressure, conf.pressure);
+        assert_eq!(again.feed_flow, conf.feed_flow);
+    }
+
+    #[test]
+    fn saves_registered_catalysts_with_their_kinetics() {
+        let vendor = CatalystKinetics::new("Vendor", KMIR_EA, KMIR_BIG_A, 0.6, 610., 780.);
+        vendor.clone().register().unwrap();
+        let inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, vendor.clone())
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .build()
+            .unwrap();
+        let conf = ReactorConfig::from_instance(&inst);
+        assert_eq!(conf.catalyst, CatalystConfig::Kinetics(vendor.clone()));
+
+        let dir = std::env::temp_dir().join(format!("hb_vendor_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for ext in ["toml", "json"] {
+            let path = dir.join(format!("vendor.{}", ext));
+            conf.save(&path).unwrap();
+
+            // a later registration under the same name must not change the saved case
+            CatalystKinetics::new("Vendor", KMIR_EA, KMIR_BIG_A, 0.4, 600., 800.)
+                .register()
+                .unwrap();
+            let loaded = ReactorConfig::load(&path).unwrap();
+            assert_eq!(loaded.catalyst_kinetics().unwrap(), vendor);
+            assert_eq!(loaded.build().unwrap().cat(), &vendor);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+
+        // the built-in catalysts are still saved by name
+        let kmir = case_studies()[0].build().unwrap();
+        assert_eq!(
+            ReactorConfig::from_instance(&kmir).catalyst,
+            CatalystConfig::Name(kmir.cat().name.clone())
+        );
+    }
+
+    #[test]
+    fn rejects_infinite_values() {
+        assert!(!is_positive(f64::INFINITY));
+        assert!(!is_positive(f64::NAN));
+        assert!(is_positive(1e-12));
+
+        let mut conf = case_studies()[0].clone();
+        conf.pressure = f64::INFINITY;
+        let content = conf.to_string(ConfigFormat::Toml).unwrap();
+        assert!(content.contains("pressure = inf"));
+        assert!(matches!(
+            ReactorConfig::parse(&content, ConfigFormat::Toml),
+            Err(ConfigError::Invalid { field, .. }) if field == "pressure"
+        ));
+    }
+}
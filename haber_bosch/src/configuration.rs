@@ -1,13 +1,21 @@
-use std::{ops::Range, str::FromStr};
+use std::{
+    fmt::Display,
+    ops::Range,
+    str::FromStr,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-use crate::simulation::{self, State, HaberBoschModel, HaberBoschSolverInfo};
+use crate::simulation::{self, HaberBoschModel, HaberBoschSolverInfo, SimulationError, State};
 
 use::itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use wasm_bindgen::prelude::*;
 
 use crate::v2_hints::*;
 
+/// The built-in catalysts, they come with tabulated bed constants (see `HaberBoschBedSetup::from_constants`).
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone)]
 pub enum Catalyst {
     KMIR,
@@ -26,15 +34,395 @@ impl FromStr for Catalyst {
     }
 }
 
+impl Catalyst {
+    pub fn kinetics(&self) -> CatalystKinetics {
+        match self {
+            Catalyst::FN => CatalystKinetics {
+                name: "FN".to_owned(),
+                ea: FN_EA,
+                big_a: FN_BIG_A,
+                alpha: FN_ALPHA,
+                t_min: FN_T_MIN,
+                t_max: FN_T_MAX,
+            },
+            Catalyst::KMIR => CatalystKinetics {
+                name: "KMIR".to_owned(),
+                ea: KMIR_EA,
+                big_a: KMIR_BIG_A,
+                alpha: KMIR_ALPHA,
+                t_min: KMIR_T_MIN,
+                t_max: KMIR_T_MAX,
+            },
+        }
+    }
+}
+
+/// Kinetic parameters of the Temkin-Pyzhev rate expression for a catalyst.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalystKinetics {
+    pub name: String,
+    /// [cal/mol] activation energy
+    pub ea: f64,
+    /// pre exponential factor
+    pub big_a: f64,
+    pub alpha: f64,
+    /// [K] lower bound of the temperature window the parameters are valid in
+    pub t_min: f64,
+    /// [K] upper bound of the temperature window, the temperature ramp is limited to it
+    pub t_max: f64,
+}
+
+/// Catalysts registered at runtime, the built-ins are always available.
+static CATALYST_REGISTRY: Mutex<Vec<CatalystKinetics>> = Mutex::new(Vec::new());
+
+/// Locks the registry, also after a thread panicked while holding the lock.
+///
+/// Only validated catalysts are pushed and replaced catalysts are removed before, so the list is
+/// consistent whenever the lock is released.
+fn catalyst_registry() -> MutexGuard<'static, Vec<CatalystKinetics>> {
+    CATALYST_REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[wasm_bindgen]
+impl CatalystKinetics {
+    pub fn new(name: &str, ea: f64, big_a: f64, alpha: f64, t_min: f64, t_max: f64) -> Self {
+        CatalystKinetics {
+            name: name.trim().to_owned(),
+            ea,
+            big_a,
+            alpha,
+            t_min,
+            t_max,
+        }
+    }
+}
+
+impl CatalystKinetics {
+    /// Checks that the parameters are physically meaningful.
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        let invalid = |msg: &str| {
+            Err(ConfigurationError::InvalidCatalyst {
+                name: self.name.clone(),
+                reason: msg.to_owned(),
+            })
+        };
+
+        if self.name.is_empty() {
+            return invalid("name is empty");
+        }
+        if !(self.ea.is_finite() && self.big_a.is_finite() && self.ea > 0. && self.big_a > 0.) {
+            return invalid("activation energy and pre exponential factor have to be positive");
+        }
+        if !(0. ..=1.).contains(&self.alpha) {
+            return invalid("alpha has to be in [0, 1]");
+        }
+        if !(self.t_min > 0. && self.t_min < self.t_max) {
+            return invalid("temperature window is empty");
+        }
+        Ok(())
+    }
+
+    /// Registers a custom catalyst, a catalyst with the same name gets replaced.
+    ///
+    /// The names of the built-in catalysts are reserved.
+    pub fn register(self) -> Result<(), ConfigurationError> {
+        self.validate()?;
+        if Catalyst::from_str(&self.name).is_ok() {
+            return Err(ConfigurationError::InvalidCatalyst {
+                name: self.name,
+                reason: "name of a built-in catalyst".to_owned(),
+            });
+        }
+
+        let mut registry = catalyst_registry();
+        registry.retain(|kin| !kin.name.eq_ignore_ascii_case(&self.name));
+        registry.push(self);
+        Ok(())
+    }
+
+    /// Finds a built-in or registered catalyst by its case insensitive name.
+    pub fn lookup(name: &str) -> Option<Self> {
+        if let Ok(cat) = Catalyst::from_str(name) {
+            return Some(cat.kinetics());
+        }
+
+        let name = name.trim();
+        catalyst_registry()
+            .iter()
+            .find(|kin| kin.name.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// The names of all available catalysts, the built-ins first.
+    pub fn registered() -> Vec<String> {
+        let mut names = vec!["KMIR".to_owned(), "FN".to_owned()];
+        names.extend(catalyst_registry().iter().map(|kin| kin.name.clone()));
+        names
+    }
+
+    /// True if the temperature `t` [K] lies inside the window the parameters are valid in.
+    pub fn in_window(&self, t: f64) -> bool {
+        (self.t_min..=self.t_max).contains(&t)
+    }
+}
+
+impl FromStr for CatalystKinetics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CatalystKinetics::lookup(s).ok_or_else(|| format!("Unknown catalyst {}", s.trim()))
+    }
+}
+
+impl From<Catalyst> for CatalystKinetics {
+    fn from(cat: Catalyst) -> Self {
+        cat.kinetics()
+    }
+}
+
+impl Display for CatalystKinetics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Catalyst {}", self.name)
+    }
+}
+
+/// How the temperature develops along a reactor bed.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureMode {
+    /// the temperature raises linearly with `t_slope` until it reaches `t_max`
+    #[default]
+    Ramp,
+    /// the temperature follows from the heat released by the exothermic reaction
+    Adiabatic,
+}
+
+/// Catalyst packing of a reactor bed, used to compute the pressure drop with the Ergun equation.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ErgunParameters {
+    /// [m] diameter of the catalyst particles
+    pub particle_diameter: f64,
+    /// [-] void fraction of the catalyst packing
+    pub void_fraction: f64,
+    /// [m/s] superficial gas velocity at the bed inlet, kept constant along the bed
+    pub superficial_velocity: f64,
+}
+
+#[wasm_bindgen]
+impl ErgunParameters {
+    pub fn new(particle_diameter: f64, void_fraction: f64, superficial_velocity: f64) -> Self {
+        ErgunParameters {
+            particle_diameter,
+            void_fraction,
+            superficial_velocity,
+        }
+    }
+}
+
+/// Errors that are detected while configuring a Haber-Bosch instance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigurationError {
+    /// The mole fraction of the named component is negative or not finite.
+    InvalidMoleFraction { component: &'static str, value: f64 },
+    /// The mole fractions of the feed do not sum up to one.
+    MoleFractionSum { sum: f64 },
+    /// The kinetic parameters of the named catalyst cannot be used.
+    InvalidCatalyst { name: String, reason: String },
+    /// The reactor needs at least one bed.
+    NoBeds,
+    /// A value of the reactor is out of range, the field is named like in the configuration
+    /// files, e.g. `beds[1].t_start`.
+    OutOfRange {
+        field: String,
+        value: f64,
+        expected: &'static str,
+    },
+}
+
+impl Display for ConfigurationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigurationError::InvalidMoleFraction { component, value } => {
+                write!(f, "Invalid mole fraction of {} in feed: {}", component, value)
+            }
+            ConfigurationError::MoleFractionSum { sum } => {
+                write!(f, "Mole fractions of feed sum up to {} instead of 1", sum)
+            }
+            ConfigurationError::InvalidCatalyst { name, reason } => {
+                write!(f, "Invalid catalyst {}: {}", name, reason)
+            }
+            ConfigurationError::NoBeds => write!(f, "At least one reactor bed is required"),
+            ConfigurationError::OutOfRange {
+                field,
+                value,
+                expected,
+            } => write!(f, "Invalid value of {}: {}, {}", field, value, expected),
+        }
+    }
+}
+
+impl std::error::Error for ConfigurationError {}
+
+/// Mole fractions of the gas entering the first reactor bed.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FeedComposition {
+    n2: f64,
+    h2: f64,
+    nh3: f64,
+    ar: f64,
+    ch4: f64,
+}
+
+impl FeedComposition {
+    pub const COMPONENTS: [&'static str; 5] = ["N2", "H2", "NH3", "Ar", "CH4"];
+
+    /// Creates a validated feed, the fractions have to be non-negative and sum up to one.
+    pub fn new(n2: f64, h2: f64, nh3: f64, ar: f64, ch4: f64) -> Result<Self, ConfigurationError> {
+        let feed = FeedComposition { n2, h2, nh3, ar, ch4 };
+
+        for (component, value) in Self::COMPONENTS.iter().zip(feed.as_array()) {
+            if !value.is_finite() || value < 0. {
+                return Err(ConfigurationError::InvalidMoleFraction { component, value });
+            }
+        }
+
+        let sum = feed.as_array().iter().sum::<f64>();
+        if (sum - 1.).abs() > FEED_SUM_TOLERANCE {
+            return Err(ConfigurationError::MoleFractionSum { sum });
+        }
+
+        // a feed without ammonia is fine, the rate expression seeds it with MIN_PARTIAL_PRESSURE
+        Ok(feed)
+    }
+
+    /// The mole fractions in the order of the state vector.
+    pub fn as_array(&self) -> [f64; 5] {
+        [self.n2, self.h2, self.nh3, self.ar, self.ch4]
+    }
+}
+
+#[wasm_bindgen]
+impl FeedComposition {
+    /// Same as `new` but usable from JS, the error is reported as string.
+    pub fn create(n2: f64, h2: f64, nh3: f64, ar: f64, ch4: f64) -> Result<FeedComposition, String> {
+        Self::new(n2, h2, nh3, ar, ch4).map_err(|e| e.to_string())
+    }
+
+    pub fn n2(&self) -> f64 {
+        self.n2
+    }
+
+    pub fn h2(&self) -> f64 {
+        self.h2
+    }
+
+    pub fn nh3(&self) -> f64 {
+        self.nh3
+    }
+
+    pub fn ar(&self) -> f64 {
+        self.ar
+    }
+
+    pub fn ch4(&self) -> f64 {
+        self.ch4
+    }
+
+    /// [-] the H2/N2 ratio, stoichiometric feed has a ratio of 3
+    pub fn h2_n2_ratio(&self) -> f64 {
+        self.h2 / self.n2
+    }
+
+    /// [-] the fraction of argon and methane
+    pub fn inerts(&self) -> f64 {
+        self.ar + self.ch4
+    }
+}
+
+impl Default for FeedComposition {
+    fn default() -> Self {
+        let [n2, h2, nh3, ar, ch4] = FEED_MOLE_FRACTIONS;
+        FeedComposition { n2, h2, nh3, ar, ch4 }
+    }
+}
+
+/// The unit between two reactor beds that brings the gas to the inlet temperature of the next bed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InterBedUnit {
+    /// indirect heat exchanger, sets the temperature to `t_start` without changing the composition
+    #[default]
+    HeatExchanger,
+    /// cold-shot quench, mixes fresh feed at `feed_temperature` [K] into the gas until it reaches `t_start`
+    Quench { feed_temperature: f64 },
+}
+
+/// Result of the unit in front of a reactor bed.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Default, Serialize)]
+pub struct InterBedResult {
+    /// [K] temperature of the gas leaving the previous bed
+    pub t_in: f64,
+    /// [K] temperature of the gas entering the bed
+    pub t_out: f64,
+    /// [kW] heat removed by the heat exchanger, zero for a quench
+    pub duty: f64,
+    /// [-] quench gas relative to the feed of the first bed, zero for a heat exchanger
+    pub quench_ratio: f64,
+    /// [mol/s] molar flow of the quench gas
+    pub quench_flow: f64,
+}
+
+impl Display for InterBedResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.quench_ratio > 0. {
+            write!(
+                f,
+                "quench with {:.1} mol/s ({:.3} of feed), {:.1} -> {:.1} °C",
+                self.quench_flow,
+                self.quench_ratio,
+                self.t_in - 273.,
+                self.t_out - 273.
+            )
+        } else {
+            write!(
+                f,
+                "heat exchanger with {:.1} kW, {:.1} -> {:.1} °C",
+                self.duty,
+                self.t_in - 273.,
+                self.t_out - 273.
+            )
+        }
+    }
+}
+
 /// This data structure answers the question:
 /// // What data needs to be store and how can we support any number of reactor beds?
 #[wasm_bindgen]
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct HaberBoschBedSetup {
     pub beta: f64,
     pub t_start: f64,
     pub t_slope: f64,
     pub t_max: f64,
+    #[serde(default)]
+    pub temperature_mode: TemperatureMode,
+    /// the pressure stays constant along the bed if there are no Ergun parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure_drop: Option<ErgunParameters>,
+    /// the unit in front of the bed, it is ignored for the first bed
+    ///
+    /// Not visible from JS, which selects the unit with `set_heat_exchanger` and `set_quench`.
+    #[wasm_bindgen(skip)]
+    #[serde(default)]
+    pub inter_bed: InterBedUnit,
 }
 
 #[wasm_bindgen]
@@ -43,6 +431,14 @@ impl HaberBoschBedSetup {
         Self::default()
     }
 
+    pub fn set_heat_exchanger(&mut self) {
+        self.inter_bed = InterBedUnit::HeatExchanger;
+    }
+
+    pub fn set_quench(&mut self, feed_temperature: f64) {
+        self.inter_bed = InterBedUnit::Quench { feed_temperature };
+    }
+
     pub fn from_constants(idx: u32, catalyst: &str) -> Self {
         let cat = Catalyst::from_str(catalyst)
             .map_err(|e| panic!("Cannot create HaberBoschBedSetup: {}", e))
@@ -55,18 +451,27 @@ impl HaberBoschBedSetup {
                     t_start: KMIR_B1_TS,
                     t_slope: KMIR_B1_TR,
                     t_max: KMIR_B1_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 1 => HaberBoschBedSetup {
                     beta: KMIR_B2_B,
                     t_start: KMIR_B2_TS,
                     t_slope: KMIR_B2_TR,
                     t_max: KMIR_B2_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 2 => HaberBoschBedSetup {
                     beta: KMIR_B3_B,
                     t_start: KMIR_B3_TS,
                     t_slope: KMIR_B3_TR,
                     t_max: KMIR_B3_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 _ => panic!("BED {} not supported yet", idx),
             },
@@ -76,18 +481,27 @@ impl HaberBoschBedSetup {
                     t_start: FN_B1_TS,
                     t_slope: FN_B1_TR,
                     t_max: FN_B1_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 1 => HaberBoschBedSetup {
                     beta: FN_B2_B,
                     t_start: FN_B2_TS,
                     t_slope: FN_B2_TR,
                     t_max: FN_B2_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 2 => HaberBoschBedSetup {
                     beta: FN_B3_B,
                     t_start: FN_B3_TS,
                     t_slope: FN_B3_TR,
                     t_max: FN_B3_TM,
+                    temperature_mode: TemperatureMode::Ramp,
+                    pressure_drop: None,
+                    inter_bed: InterBedUnit::HeatExchanger,
                 },
                 _ => panic!("BED {} not supported yet", idx),
             },
@@ -96,13 +510,142 @@ impl HaberBoschBedSetup {
 }
 
 
+/// The ODE solvers that can be used to integrate the reactor beds.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolverKind {
+    /// explicit Runge-Kutta method of order 5(4), stops via solout()
+    #[default]
+    Dopri5,
+    /// explicit Runge-Kutta method of order 8(5,3), solout() is emulated
+    Dop853,
+    /// classic Runge-Kutta method with fixed step size, solout() is emulated
+    Rk4,
+    /// linearly implicit Rosenbrock method of order 2(1) for the stiff regime
+    Rosenbrock,
+}
+
+impl FromStr for SolverKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "dopri5" => Ok(SolverKind::Dopri5),
+            "dop853" => Ok(SolverKind::Dop853),
+            "rk4" => Ok(SolverKind::Rk4),
+            "rosenbrock" => Ok(SolverKind::Rosenbrock),
+            _ => Err("Unknown solver".to_owned()),
+        }
+    }
+}
+
+/// Settings for the numerical integration of the reactor beds.
+///
+/// The defaults reproduce the figures of the video series.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverSettings {
+    /// the ODE solver used for the integration
+    pub kind: SolverKind,
+    /// maximum length of a single reactor bed, the integration of a bed ends there at the latest
+    pub max_bed_length: f64,
+    /// distance between two points of the dense solver output, Rk4 uses it as step size
+    pub output_step: f64,
+    /// relative tolerance of the adaptive step size control (not used by Rk4), at least 1e-6 for
+    /// the Rosenbrock solver
+    pub rtol: f64,
+    /// absolute tolerance of the adaptive step size control (not used by Rk4), at least 1e-9 for
+    /// the Rosenbrock solver
+    pub atol: f64,
+    /// the integration of a bed stops when the change of ammonia drops below this threshold
+    pub stop_threshold: f64,
+    /// maximum number of integration steps per bed
+    pub max_steps: u32,
+}
+
+#[wasm_bindgen]
+impl SolverSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings {
+            kind: SolverKind::Dopri5,
+            max_bed_length: 25.,
+            output_step: 25. / 2000.,
+            rtol: 10e-12,
+            atol: 10e-16,
+            stop_threshold: AMMONIA_THRESHOLD,
+            max_steps: 100000,
+        }
+    }
+}
+
+/// The reason why the integration of a reactor bed ended.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// solout() stopped the solver because the change of ammonia dropped below the threshold
+    AmmoniaThreshold,
+    /// like AmmoniaThreshold, but the bed temperature already reached t_max
+    TemperatureLimit,
+    /// the end of the integration span (max bed length) was reached
+    EndOfSpan,
+}
+
+impl Display for TerminationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerminationReason::AmmoniaThreshold => write!(f, "ammonia threshold"),
+            TerminationReason::TemperatureLimit => write!(f, "ammonia threshold at t_max"),
+            TerminationReason::EndOfSpan => write!(f, "end of span"),
+        }
+    }
+}
+
+/// Statistics of the ODE solver for a single reactor bed.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct BedSolverStats {
+    pub num_eval: u32,
+    pub accepted_steps: u32,
+    pub rejected_steps: u32,
+    /// wall-clock time of the integration in seconds
+    pub wall_time: f64,
+    pub termination: TerminationReason,
+}
+
+impl Display for BedSolverStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} evaluations, {} accepted / {} rejected steps, {:.3} ms, stopped by {}",
+            self.num_eval,
+            self.accepted_steps,
+            self.rejected_steps,
+            self.wall_time * 1000.,
+            self.termination
+        )
+    }
+}
+
 /// This data structure answers the question:
 /// Use this structure to store results of ODE-solver (x_out and y_out), what is the dimensionality of y_out?
 #[derive(Debug, Clone)]
 pub struct HaberBoschBedResult {
     pub x_out: Vec<f64>,
     pub y_out: Vec<simulation::State>, 
-    // --> simulation::State is 6D vector (nitrogen, hydrogen, ammonia, argon, methan and temperature)
+    // --> simulation::State is 7D vector (nitrogen, hydrogen, ammonia, argon, methan, temperature
+    // and pressure)
+    pub stats: BedSolverStats,
+    /// the result of the unit in front of the bed, None for the first bed
+    pub inlet: Option<InterBedResult>,
 }
 
 // Introduce a nested data-structure that is capable of representing a Haber-Bosch configuration in configuration.rs, think about:
@@ -114,7 +657,10 @@ pub struct HaberBoschBedResult {
 #[derive(Debug, Clone)]
 pub struct HaberBoschInstance { 
     partial_pressure: f64,
-    catalyst: Catalyst,
+    catalyst: CatalystKinetics,
+    solver_settings: SolverSettings,
+    feed_flow: f64,
+    feed: FeedComposition,
 
     reactor_beds: Vec<HaberBoschBedSetup>,
     reactor_results: Vec<HaberBoschBedResult>,
@@ -126,20 +672,100 @@ impl HaberBoschInstance {
         self.reactor_beds.len()
     }
 
-    pub fn cat(&self) -> Catalyst {
-        self.catalyst
+    pub fn is_empty(&self) -> bool {
+        self.reactor_beds.is_empty()
+    }
+
+    pub fn num_results(&self) -> usize {
+        self.reactor_results.len()
+    }
+
+    pub fn cat(&self) -> &CatalystKinetics {
+        &self.catalyst
     }
 
     pub fn pres(&self) -> f64 {
         self.partial_pressure
     }
 
-    pub fn add_next_results(&mut self, x: Vec<f64>, y: Vec<simulation::State>) {
+    pub fn solver_settings(&self) -> SolverSettings {
+        self.solver_settings
+    }
+
+    /// [mol/s] molar flow entering the first bed
+    pub fn feed_flow(&self) -> f64 {
+        self.feed_flow
+    }
+
+    pub fn feed(&self) -> FeedComposition {
+        self.feed
+    }
+
+    pub fn beds(&self) -> &[HaberBoschBedSetup] {
+        &self.reactor_beds
+    }
+
+    pub fn results(&self) -> &[HaberBoschBedResult] {
+        &self.reactor_results
+    }
+
+    /// Returns a copy without results that is fed by `feed_flow` [mol/s] of the given composition.
+    pub fn with_feed(&self, feed: FeedComposition, feed_flow: f64) -> HaberBoschInstance {
+        HaberBoschInstance {
+            feed,
+            feed_flow,
+            reactor_results: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// [mol/s] molar flows of the five gas components leaving the last simulated bed
+    pub fn outlet_flows(&self) -> Option<[f64; 5]> {
+        let last_y = self.reactor_results.last()?.y_out.last()?;
+        let flow_per_pressure = self.flow_per_pressure(self.reactor_results.len() - 1);
+        let mut flows = [0.; 5];
+        for (idx, flow) in flows.iter_mut().enumerate() {
+            *flow = flow_per_pressure * last_y[idx];
+        }
+        Some(flows)
+    }
+
+    /// [mol/s per bar] converts the state of the simulated bed `idx` into molar flows
+    ///
+    /// The feed flow corresponds to the inlet pressure of the first bed. A quench adds gas but
+    /// scales the mixed state back to the inlet pressure, so every quench changes the factor.
+    pub fn flow_per_pressure(&self, idx: usize) -> f64 {
+        let n_total = |y: &State| y.iter().take(5).sum::<f64>();
+        let mut flow_per_pressure = self.feed_flow / self.pres();
+        for (prev, res) in self.reactor_results.iter().zip(&self.reactor_results[1..=idx]) {
+            let quench_flow = res.inlet.map_or(0., |unit| unit.quench_flow);
+            let flow_in = flow_per_pressure * n_total(prev.y_out.last().unwrap()) + quench_flow;
+            flow_per_pressure = flow_in / n_total(res.y_out.first().unwrap());
+        }
+        flow_per_pressure
+    }
+
+    pub fn add_next_results(&mut self, result: HaberBoschBedResult) -> Result<(), SimulationError> {
+        // there cannot be more results then beds
         if self.reactor_results.len() >= self.reactor_beds.len() {
-            panic!("There cannot be more results then beds");
+            return Err(SimulationError::BedCountMismatch {
+                beds: self.reactor_beds.len(),
+                results: self.reactor_results.len() + 1,
+            });
         }
 
-        self.reactor_results.push(HaberBoschBedResult { x_out: x, y_out: y })
+        self.reactor_results.push(result);
+        Ok(())
+    }
+
+    /// The solver statistics of all simulated beds.
+    pub fn bed_stats(&self) -> Vec<BedSolverStats> {
+        self.reactor_results.iter().map(|r| r.stats).collect()
+    }
+
+    /// The results of the units between the simulated beds.
+    pub fn inter_bed_results(&self) -> Vec<InterBedResult> {
+        self.reactor_results.iter().filter_map(|r| r.inlet).collect()
     }
 
     pub fn get_solver_info(&self, idx: usize) -> simulation::HaberBoschSolverInfo {
@@ -147,59 +773,43 @@ impl HaberBoschInstance {
             panic!("Not enough results");
         }
 
-        let model = HaberBoschModel::new(
+        let mut model = HaberBoschModel::new(
             self.pres(), 
             self.cat(), 
             self.reactor_beds[idx]);
+        model.stop_threshold = self.solver_settings.stop_threshold;
 
         let x0 = if idx == 0 {
             0.
         } else {
-           *self.reactor_results[idx-1].x_out.iter().last().unwrap()
+           *self.reactor_results[idx-1].x_out.last().unwrap()
         };
 
-        let mut y0 = if idx == 0 {
-        
-            // the partial gas pressure
-            let mut pp = State::new(
-                0.2391,
-                0.623,
-                0.0413,
-                0.0793,
-                0.0172,
-                0.,
-            );
-            pp = pp * self.pres();
-            pp
+        let (y0, inlet) = if idx == 0 {
+            let mut y0 = self.feed_state();
+            y0[5] = self.reactor_beds[idx].t_start;
+            (y0, None)
         } else {
-            *self.reactor_results[idx-1].y_out.iter().last().unwrap()
+            let (y0, inlet) = simulation::inter_bed_unit(
+                self,
+                self.reactor_beds[idx],
+                self.reactor_results[idx-1].y_out.last().unwrap(),
+                self.flow_per_pressure(idx - 1),
+            );
+            (y0, Some(inlet))
         };
-        y0[5] = self.reactor_beds[idx].t_start;
 
-        HaberBoschSolverInfo { model, x0, y0 }
+        HaberBoschSolverInfo { model, x0, y0, settings: self.solver_settings, inlet }
     }
 
-    pub fn print_summary(&self) {
-        let len_iter = self.reactor_results.iter().map(|el| *el.x_out.last().unwrap());
-
-        println!("Summary based on {}", self.catalyst.to_string());
-
-        print!("Reactor Length: ");
-        let mut before = 0.;
-        for (idx, len) in len_iter.clone().enumerate() {
-            if idx > 0 {
-                print!(" + ");
-            }
-            print!("{:.3}", len - before);
-            before = before + len;
+    /// The partial gas pressures of the feed entering the first bed, the temperature is zero.
+    pub fn feed_state(&self) -> State {
+        let mut pp = State::zeros();
+        for (idx, frac) in self.feed.as_array().iter().enumerate() {
+            pp[idx] = frac * self.pres();
         }
-        println!(" = {:.3}", len_iter.last().unwrap());
-
-        let last_y = self.reactor_results.iter().last().unwrap().y_out.last().unwrap();
-        println!(
-            "Final Yield: {}\n",
-            last_y[2] / (last_y.iter().take(5).sum::<f64>())
-        );
+        pp[6] = self.pres();
+        pp
     }
 
     pub fn get_temperature_range(&self) -> Range<f32> {
@@ -208,7 +818,7 @@ impl HaberBoschInstance {
             .flat_map(|x| x.y_out.iter())
             .map(|mat| mat[5] as f32 - 273f32)
             .minmax_by(|lhs, rhs| {
-                lhs.partial_cmp(rhs).unwrap()
+                lhs.total_cmp(rhs)
             });
 
         match min_max_res {
@@ -222,15 +832,14 @@ impl HaberBoschInstance {
         let last = self
             .reactor_results
             .iter()
-            .map(|r| r.x_out.iter().last().unwrap())
-            .rev()
-            .nth(0)
+            .map(|r| r.x_out.last().unwrap())
+            .next_back()
             .unwrap();
         0f32..(*last as f32)
     }
 
     pub fn get_concentration_range(&self) -> Range<f32> {
-        let cmp = |a: &f64, b: &f64| a.partial_cmp(b).unwrap();
+        let cmp = |a: &f64, b: &f64| a.total_cmp(b);
 
         let max_conc = self
             .reactor_results
@@ -250,7 +859,7 @@ impl HaberBoschInstance {
         component: usize,
         normalize: bool,
     ) -> MyIterator<'a> {
-        if component > 5 {
+        if component > 6 {
             panic!("Component too high");
         }
 
@@ -291,21 +900,23 @@ impl<'a> Iterator for MyIterator<'a> {
 
             if self.comp_idx == 5 {
                 // temperature
-                y = y - 273.;
+                y -= 273.;
+            } else if self.comp_idx == 6 {
+                // pressure is never normalized
             } else if self.normalize {
                 let sum: f32 = comp
                     .iter()
                     .take(5)
                     .map(|x| *x as f32)
                     .sum();
-                y = y /sum;
+                y /= sum;
             }
 
             // point to next element
-            self.ele_idx = self.ele_idx + 1;
+            self.ele_idx += 1;
             if self.ele_idx >= bed_res.x_out.len() {
                 self.ele_idx = 0;
-                self.bed_idx = self.bed_idx + 1;
+                self.bed_idx += 1;
             }
 
             Some((x, y))
@@ -322,40 +933,406 @@ impl<'a> Iterator for MyIterator<'a> {
 
 
 
+/// True for positive, finite numbers, false for NaN and infinity.
+pub(crate) fn is_positive(x: f64) -> bool {
+    x.is_finite() && x > 0.
+}
+
 pub struct HaberBoschInstanceBuilder {
     wip: HaberBoschInstance,
 }
 
 impl HaberBoschInstanceBuilder {
-    pub fn create(p: f64, c: Catalyst) -> Self {
+    pub fn create(p: f64, c: impl Into<CatalystKinetics>) -> Self {
         HaberBoschInstanceBuilder { 
             wip: HaberBoschInstance { 
                 partial_pressure: p, 
-                catalyst: c, 
+                catalyst: c.into(), 
+                solver_settings: SolverSettings::default(),
+                feed_flow: FEED_MOLAR_FLOW,
+                feed: FeedComposition::default(),
                 reactor_beds: vec![], 
                 reactor_results: vec![] 
         } }
     }
 
     pub fn add_bed(mut self, t_start: f64, t_slope: f64, t_max: f64, beta: f64) -> Self {
-        self.wip.reactor_beds.push(HaberBoschBedSetup { beta, t_start, t_slope, t_max });
+        self.wip.reactor_beds.push(HaberBoschBedSetup {
+            beta,
+            t_start,
+            t_slope,
+            t_max,
+            temperature_mode: TemperatureMode::Ramp,
+            pressure_drop: None,
+            inter_bed: InterBedUnit::HeatExchanger,
+        });
+        self
+    }
+
+    pub fn add_bed_setup(mut self, bed: HaberBoschBedSetup) -> Self {
+        self.wip.reactor_beds.push(bed);
+        self
+    }
+
+    pub fn with_solver_settings(mut self, settings: SolverSettings) -> Self {
+        self.wip.solver_settings = settings;
+        self
+    }
+
+    pub fn with_feed_flow(mut self, feed_flow: f64) -> Self {
+        self.wip.feed_flow = feed_flow;
         self
     }
 
-    pub fn build(self) -> HaberBoschInstance {
-        if self.wip.reactor_beds.is_empty() {
-            panic!("Reactor bed is require");
+    pub fn with_feed_composition(mut self, feed: FeedComposition) -> Self {
+        self.wip.feed = feed;
+        self
+    }
+
+    /// Checks the setup, the kinetics would produce NaN or infinite values for
+    /// non-positive temperatures and pressures.
+    pub fn build(self) -> Result<HaberBoschInstance, ConfigurationError> {
+        let wip = &self.wip;
+        let out_of_range = |field: String, value: f64, expected: &'static str| {
+            Err(ConfigurationError::OutOfRange { field, value, expected })
+        };
+
+        if !is_positive(wip.partial_pressure) {
+            return out_of_range("pressure".to_owned(), wip.partial_pressure, "has to be positive");
+        }
+        if !is_positive(wip.feed_flow) {
+            return out_of_range("feed_flow".to_owned(), wip.feed_flow, "has to be positive");
         }
+        wip.catalyst.validate()?;
 
-        self.wip
+        let solver = &wip.solver_settings;
+        if !is_positive(solver.max_bed_length) {
+            return out_of_range(
+                "solver.max_bed_length".to_owned(),
+                solver.max_bed_length,
+                "has to be positive",
+            );
+        }
+        if !(is_positive(solver.output_step) && solver.output_step < solver.max_bed_length) {
+            return out_of_range(
+                "solver.output_step".to_owned(),
+                solver.output_step,
+                "has to be positive and shorter than max_bed_length",
+            );
+        }
+        for (field, value) in [("solver.rtol", solver.rtol), ("solver.atol", solver.atol)] {
+            if !is_positive(value) {
+                return out_of_range(field.to_owned(), value, "has to be positive");
+            }
+        }
+        // a NaN threshold would stop every bed after its first output point
+        if !is_positive(solver.stop_threshold) {
+            return out_of_range(
+                "solver.stop_threshold".to_owned(),
+                solver.stop_threshold,
+                "has to be positive",
+            );
+        }
+        if solver.max_steps == 0 {
+            return out_of_range("solver.max_steps".to_owned(), 0., "has to be at least 1");
+        }
+        // a second order method cannot reach tighter tolerances in reasonable time
+        if solver.kind == SolverKind::Rosenbrock {
+            if solver.rtol < ROSENBROCK_MIN_RTOL {
+                return out_of_range(
+                    "solver.rtol".to_owned(),
+                    solver.rtol,
+                    "has to be at least 1e-6 for the rosenbrock solver",
+                );
+            }
+            if solver.atol < ROSENBROCK_MIN_ATOL {
+                return out_of_range(
+                    "solver.atol".to_owned(),
+                    solver.atol,
+                    "has to be at least 1e-9 for the rosenbrock solver",
+                );
+            }
+        }
+
+        if wip.reactor_beds.is_empty() {
+            return Err(ConfigurationError::NoBeds);
+        }
+        for (idx, bed) in wip.reactor_beds.iter().enumerate() {
+            let field = |name: &str| format!("beds[{}].{}", idx, name);
+            if !is_positive(bed.t_start) {
+                return out_of_range(field("t_start"), bed.t_start, "has to be a temperature in K");
+            }
+            if !(bed.t_max.is_finite() && bed.t_max > bed.t_start) {
+                return out_of_range(field("t_max"), bed.t_max, "has to be larger than t_start");
+            }
+            // the kinetics are only valid in the window of the catalyst, the ramp must not leave it
+            let window = wip.catalyst.t_min..=wip.catalyst.t_max;
+            for (name, value) in [("t_start", bed.t_start), ("t_max", bed.t_max)] {
+                if !window.contains(&value) {
+                    return out_of_range(
+                        field(name),
+                        value,
+                        "has to be within the temperature window of the catalyst",
+                    );
+                }
+            }
+            if !(bed.t_slope.is_finite() && bed.beta.is_finite()) {
+                let value = if bed.t_slope.is_finite() { bed.beta } else { bed.t_slope };
+                return out_of_range(field("t_slope"), value, "t_slope and beta have to be finite");
+            }
+            if let InterBedUnit::Quench { feed_temperature } = bed.inter_bed {
+                if !is_positive(feed_temperature) {
+                    return out_of_range(
+                        field("inter_bed.feed_temperature"),
+                        feed_temperature,
+                        "has to be a temperature in K",
+                    );
+                }
+            }
+            if let Some(ergun) = bed.pressure_drop {
+                if !(ergun.void_fraction > 0. && ergun.void_fraction < 1.) {
+                    return out_of_range(
+                        field("pressure_drop.void_fraction"),
+                        ergun.void_fraction,
+                        "has to be in (0, 1)",
+                    );
+                }
+                for (name, value) in [
+                    ("particle_diameter", ergun.particle_diameter),
+                    ("superficial_velocity", ergun.superficial_velocity),
+                ] {
+                    if !is_positive(value) {
+                        return out_of_range(
+                            field(&format!("pressure_drop.{}", name)),
+                            value,
+                            "has to be positive",
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(self.wip)
     }
 }
 
-impl ToString for Catalyst {
-    fn to_string(&self) -> String {
+impl Display for Catalyst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Catalyst::FN => "Catalyst FN".to_owned(),
-            Catalyst::KMIR => "Catalyst KMIR".to_owned(),
+            Catalyst::FN => write!(f, "Catalyst FN"),
+            Catalyst::KMIR => write!(f, "Catalyst KMIR"),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kmir() -> HaberBoschInstanceBuilder {
+        HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+    }
+
+    fn out_of_range_field(res: Result<HaberBoschInstance, ConfigurationError>) -> String {
+        match res {
+            Err(ConfigurationError::OutOfRange { field, .. }) => field,
+            other => panic!("expected an out of range error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn builds_valid_instance() {
+        assert_eq!(kmir().build().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_missing_beds() {
+        let res = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR).build();
+        assert!(matches!(res, Err(ConfigurationError::NoBeds)));
+    }
+
+    #[test]
+    fn rejects_invalid_pressure_and_flow() {
+        let res = HaberBoschInstanceBuilder::create(0., Catalyst::KMIR)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .build();
+        assert_eq!(out_of_range_field(res), "pressure");
+
+        let res = HaberBoschInstanceBuilder::create(f64::NAN, Catalyst::KMIR)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .build();
+        assert_eq!(out_of_range_field(res), "pressure");
+
+        assert_eq!(out_of_range_field(kmir().with_feed_flow(-1.).build()), "feed_flow");
+    }
+
+    #[test]
+    fn rejects_invalid_bed_temperatures() {
+        for t_start in [0., -10., f64::NAN, f64::INFINITY] {
+            let res = kmir().add_bed(t_start, 10., KMIR_B1_TM, KMIR_B1_B).build();
+            assert_eq!(out_of_range_field(res), "beds[1].t_start");
+        }
+
+        let res = kmir().add_bed(KMIR_B1_TM, 10., KMIR_B1_TS, KMIR_B1_B).build();
+        assert_eq!(out_of_range_field(res), "beds[1].t_max");
+
+        let mut bed = HaberBoschBedSetup::from_constants(2, "KMIR");
+        bed.set_quench(0.);
+        assert_eq!(
+            out_of_range_field(kmir().add_bed_setup(bed).build()),
+            "beds[1].inter_bed.feed_temperature"
+        );
+    }
+
+    #[test]
+    fn rejects_beds_outside_the_catalyst_window() {
+        let res = kmir().add_bed(KMIR_T_MIN - 1., 10., KMIR_B1_TM, KMIR_B1_B).build();
+        assert_eq!(out_of_range_field(res), "beds[1].t_start");
+
+        let res = kmir().add_bed(KMIR_B2_TS, 10., KMIR_T_MAX + 1., KMIR_B2_B).build();
+        assert_eq!(out_of_range_field(res), "beds[1].t_max");
+
+        // the limits themselves are fine, as is a window only the other catalyst allows
+        assert!(kmir().add_bed(KMIR_T_MIN, 10., KMIR_T_MAX, KMIR_B2_B).build().is_ok());
+        let res = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::FN)
+            .add_bed(FN_T_MIN, 10., FN_T_MAX, KMIR_B1_B)
+            .build();
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_solver_settings() {
+        let settings = SolverSettings {
+            output_step: 0.,
+            ..Default::default()
+        };
+        let res = kmir().with_solver_settings(settings).build();
+        assert_eq!(out_of_range_field(res), "solver.output_step");
+
+        for stop_threshold in [0., f64::NAN] {
+            let settings = SolverSettings {
+                stop_threshold,
+                ..Default::default()
+            };
+            let res = kmir().with_solver_settings(settings).build();
+            assert_eq!(out_of_range_field(res), "solver.stop_threshold");
         }
+
+        let settings = SolverSettings {
+            max_steps: 0,
+            ..Default::default()
+        };
+        let res = kmir().with_solver_settings(settings).build();
+        assert_eq!(out_of_range_field(res), "solver.max_steps");
+    }
+
+    #[test]
+    fn passes_the_solver_settings_to_the_solver() {
+        // without a ramp the reaction slows down gradually
+        let simulate = |settings: SolverSettings| {
+            let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+                .with_solver_settings(settings)
+                .add_bed(KMIR_B1_TS, 0., KMIR_B1_TM, KMIR_B1_B)
+                .build()
+                .unwrap();
+            crate::simulation::sequential_simulation(&mut inst, false).unwrap();
+            inst.results()[0].clone()
+        };
+        let reference = simulate(SolverSettings::default());
+        assert_eq!(reference.stats.termination, TerminationReason::AmmoniaThreshold);
+        let length = |res: &HaberBoschBedResult| res.x_out.last().unwrap() - res.x_out[0];
+
+        let short = simulate(SolverSettings {
+            max_bed_length: 0.2,
+            output_step: 0.01,
+            ..Default::default()
+        });
+        assert_eq!(short.stats.termination, TerminationReason::EndOfSpan);
+        // the dense output ends within one output step before the end of the span
+        assert!(length(&short) <= 0.2 + 1e-9 && length(&short) > 0.19 - 1e-9);
+
+        // a higher threshold stops the bed earlier
+        let early = simulate(SolverSettings {
+            stop_threshold: 10. * AMMONIA_THRESHOLD,
+            ..Default::default()
+        });
+        assert_eq!(early.stats.termination, TerminationReason::AmmoniaThreshold);
+        assert!(length(&early) < length(&reference));
+    }
+
+    #[test]
+    fn rejects_tolerances_the_rosenbrock_solver_cannot_meet() {
+        let settings = SolverSettings {
+            kind: SolverKind::Rosenbrock,
+            ..Default::default()
+        };
+        let res = kmir().with_solver_settings(settings).build();
+        assert_eq!(out_of_range_field(res), "solver.rtol");
+
+        let settings = SolverSettings {
+            rtol: ROSENBROCK_MIN_RTOL,
+            ..settings
+        };
+        let res = kmir().with_solver_settings(settings).build();
+        assert_eq!(out_of_range_field(res), "solver.atol");
+
+        let settings = SolverSettings {
+            atol: ROSENBROCK_MIN_ATOL,
+            ..settings
+        };
+        assert!(kmir().with_solver_settings(settings).build().is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn accepts_feed_without_ammonia() {
+        let feed = FeedComposition::new(0.25, 0.75, 0., 0., 0.).unwrap();
+        assert_eq!(feed.nh3(), 0.);
+        assert!(FeedComposition::new(0.25, 0.75, -0.1, 0., 0.).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_feed() {
+        assert!(matches!(
+            FeedComposition::new(-0.25, 1.25, 0., 0., 0.),
+            Err(ConfigurationError::InvalidMoleFraction { component: "N2", .. })
+        ));
+        assert!(matches!(
+            FeedComposition::new(1.25, -0.25, 0., 0., 0.),
+            Err(ConfigurationError::InvalidMoleFraction { component: "H2", .. })
+        ));
+        assert!(matches!(
+            FeedComposition::new(0.25, 0.75, 0., f64::NAN, 0.),
+            Err(ConfigurationError::InvalidMoleFraction { component: "Ar", .. })
+        ));
+        assert!(matches!(
+            FeedComposition::new(0.25, f64::NAN, 0., 0., 0.),
+            Err(ConfigurationError::InvalidMoleFraction { component: "H2", .. })
+        ));
+
+        let sum = 1. + 2. * FEED_SUM_TOLERANCE;
+        assert!(matches!(
+            FeedComposition::new(0.25, 0.75, 0., 2. * FEED_SUM_TOLERANCE, 0.),
+            Err(ConfigurationError::MoleFractionSum { sum: s }) if s == sum
+        ));
+        assert!(matches!(
+            FeedComposition::new(0.2, 0.7, 0., 0., 0.),
+            Err(ConfigurationError::MoleFractionSum { .. })
+        ));
+        // rounding of the inputs is tolerated
+        assert!(FeedComposition::new(0.25, 0.75, 0., FEED_SUM_TOLERANCE / 2., 0.).is_ok());
+    }
+
+    #[test]
+    fn registry_survives_a_panicking_thread() {
+        let _ = std::thread::spawn(|| {
+            let _registry = catalyst_registry();
+            panic!("poisons the registry");
+        })
+        .join();
+
+        let kinetics = CatalystKinetics::new("Poisoned", KMIR_EA, KMIR_BIG_A, 0.5, 600., 800.);
+        kinetics.clone().register().unwrap();
+        assert_eq!(CatalystKinetics::lookup("poisoned"), Some(kinetics));
+        assert!(CatalystKinetics::registered().contains(&"Poisoned".to_owned()));
+    }
+}
@@ -0,0 +1,183 @@
+//! Reaction rate diagnostics along the reactor beds.
+//!
+//! The simulation results only contain the integrated state. [`RateDiagnostics`] evaluates the
+//! rate expression again at every output point, so the rate constant, the equilibrium constant,
+//! the forward and reverse terms and the fugacity coefficients can be plotted or exported. A bed
+//! that stalls shows an approach to equilibrium close to one, a bed that is too cold a small
+//! rate constant.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::configuration::HaberBoschInstance;
+use crate::export::ExportError;
+use crate::simulation::{HaberBoschModel, RateTerms};
+
+/// The rate terms at one output point of the solver.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct RatePoint {
+    /// the reactor bed, starting at 1
+    pub bed: u32,
+    pub length: f64,
+    /// [K]
+    pub temperature: f64,
+    /// [bar]
+    pub pressure: f64,
+    pub terms: RateTerms,
+}
+
+/// Iterator over the rate terms of all output points of a simulated instance.
+#[derive(Debug, Clone)]
+pub struct RateDiagnostics<'a> {
+    instance: &'a HaberBoschInstance,
+    bed_idx: usize,
+    ele_idx: usize,
+    model: Option<HaberBoschModel>,
+}
+
+impl<'a> Iterator for RateDiagnostics<'a> {
+    type Item = RatePoint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let results = self.instance.results();
+        let bed_res = results.get(self.bed_idx)?;
+        let instance = self.instance;
+        let bed_idx = self.bed_idx;
+        let model = self.model.get_or_insert_with(|| {
+            HaberBoschModel::new(instance.pres(), instance.cat(), instance.beds()[bed_idx])
+        });
+
+        let y = bed_res.y_out[self.ele_idx];
+        let point = RatePoint {
+            bed: self.bed_idx as u32 + 1,
+            length: bed_res.x_out[self.ele_idx],
+            temperature: y[5],
+            pressure: y[6],
+            terms: model.rate_terms(&y),
+        };
+
+        // point to next element
+        self.ele_idx += 1;
+        if self.ele_idx >= bed_res.x_out.len() {
+            self.ele_idx = 0;
+            self.bed_idx += 1;
+            self.model = None;
+        }
+        Some(point)
+    }
+}
+
+impl HaberBoschInstance {
+    /// The rate terms along all beds, empty if the instance has not been simulated yet.
+    pub fn rate_diagnostics(&self) -> RateDiagnostics<'_> {
+        RateDiagnostics {
+            instance: self,
+            bed_idx: 0,
+            ele_idx: 0,
+            model: None,
+        }
+    }
+
+    /// Writes the rate terms as CSV with a header line, temperatures are given in Kelvin.
+    pub fn write_rate_diagnostics_csv<W: std::io::Write>(
+        &self,
+        writer: W,
+    ) -> Result<(), ExportError> {
+        if self.num_results() == 0 {
+            return Err(ExportError::NoResults);
+        }
+
+        let mut wtr = csv::Writer::from_writer(writer);
+        wtr.write_record([
+            "bed",
+            "length",
+            "temperature_k",
+            "pressure_bar",
+            "k",
+            "ka",
+            "t1",
+            "t2",
+            "rnh3",
+            "approach",
+            "fugacity_n2",
+            "fugacity_h2",
+            "fugacity_nh3",
+        ])?;
+        for point in self.rate_diagnostics() {
+            let terms = &point.terms;
+            let mut record = vec![point.bed.to_string()];
+            record.extend(
+                [
+                    point.length,
+                    point.temperature,
+                    point.pressure,
+                    terms.k,
+                    terms.ka,
+                    terms.t1,
+                    terms.t2,
+                    terms.rnh3,
+                    terms.approach(),
+                ]
+                .iter()
+                .chain(terms.fugacity.iter())
+                .map(|v| v.to_string()),
+            );
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn export_rate_diagnostics_csv(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let file = std::fs::File::create(path)?;
+        self.write_rate_diagnostics_csv(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{equilibrium_constant, fugacity_coefficients};
+    use crate::test_fixtures::{kmir, simulated_kmir};
+
+    #[test]
+    fn writes_the_rate_terms_of_every_output_point() {
+        let inst = simulated_kmir(2);
+        let mut buffer = vec![];
+        inst.write_rate_diagnostics_csv(&mut buffer).unwrap();
+
+        let mut rdr = csv::Reader::from_reader(buffer.as_slice());
+        assert_eq!(rdr.headers().unwrap().len(), 13);
+        assert_eq!(&rdr.headers().unwrap()[5], "ka");
+        let records: Vec<Vec<f64>> = rdr
+            .records()
+            .map(|rec| rec.unwrap().iter().map(|v| v.parse().unwrap()).collect())
+            .collect();
+
+        let y_out: Vec<_> = inst.results().iter().flat_map(|res| &res.y_out).collect();
+        assert_eq!(records.len(), y_out.len());
+        assert_eq!(inst.rate_diagnostics().count(), y_out.len());
+        assert_eq!(records.first().unwrap()[0], 1.);
+        assert_eq!(records.last().unwrap()[0], 2.);
+
+        for (rec, y) in records.iter().zip(y_out) {
+            let beta = inst.beds()[rec[0] as usize - 1].beta;
+            assert_eq!(rec[2], y[5]);
+            assert_eq!(rec[3], y[6]);
+            assert_eq!(rec[5], equilibrium_constant(y[5], beta));
+            assert_eq!(rec[9], rec[7] / rec[6]);
+            assert_eq!(rec[10..13], fugacity_coefficients(y[5], y[6]));
+        }
+    }
+
+    #[test]
+    fn refuses_to_write_without_results() {
+        let inst = kmir(2);
+        assert_eq!(inst.rate_diagnostics().count(), 0);
+        assert!(matches!(
+            inst.write_rate_diagnostics_csv(vec![]),
+            Err(ExportError::NoResults)
+        ));
+    }
+}
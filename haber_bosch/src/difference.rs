@@ -0,0 +1,198 @@
+//! Differences between two simulated scenarios.
+//!
+//! The solver picks its own output points, so the `x_out` grids of two runs differ. Both profiles
+//! are interpolated linearly onto a common length grid over the length both reactors share. At a
+//! bed boundary the outlet and the inlet of the next bed sit at the same length, so the grid
+//! point takes the inlet of the next bed.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::configuration::HaberBoschInstance;
+use crate::simulation::State;
+use crate::summary::nh3_fraction;
+
+/// The profiles of one scenario minus the profiles of the base scenario.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileDifference {
+    pub length: Vec<f64>,
+    /// [-] difference of the ammonia mole fractions
+    pub nh3: Vec<f64>,
+    /// [K] difference of the temperatures
+    pub temperature: Vec<f64>,
+}
+
+impl ProfileDifference {
+    /// (length, ΔNH3) pairs for plotting.
+    pub fn nh3_points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.length
+            .iter()
+            .zip(&self.nh3)
+            .map(|(x, d)| (*x as f32, *d as f32))
+    }
+
+    /// (length, ΔT) pairs for plotting.
+    pub fn temperature_points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.length
+            .iter()
+            .zip(&self.temperature)
+            .map(|(x, d)| (*x as f32, *d as f32))
+    }
+}
+
+/// A key figure of the base and the other scenario.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDelta {
+    pub name: String,
+    pub base: f64,
+    pub other: f64,
+    /// other - base
+    pub delta: f64,
+}
+
+impl MetricDelta {
+    fn new(name: &str, base: f64, other: f64) -> Self {
+        MetricDelta {
+            name: name.to_owned(),
+            base,
+            other,
+            delta: other - base,
+        }
+    }
+}
+
+/// The (length, value) pairs of all beds in the order of the results.
+fn profile(inst: &HaberBoschInstance, value: impl Fn(&State) -> f64) -> Vec<(f64, f64)> {
+    inst.results()
+        .iter()
+        .flat_map(|res| res.x_out.iter().copied().zip(res.y_out.iter().map(&value)))
+        .collect()
+}
+
+/// Linear interpolation in a profile sorted by length, constant beyond its ends.
+fn interpolate(profile: &[(f64, f64)], x: f64) -> f64 {
+    let idx = profile.partition_point(|(px, _)| *px <= x);
+    match (idx.checked_sub(1).map(|i| profile[i]), profile.get(idx).copied()) {
+        (Some((x0, y0)), Some((x1, y1))) => y0 + (y1 - y0) * (x - x0) / (x1 - x0),
+        (Some((_, y)), None) | (None, Some((_, y))) => y,
+        (None, None) => f64::NAN,
+    }
+}
+
+impl HaberBoschInstance {
+    /// The differences of the NH3 fraction and the temperature to `base` at `steps` lengths.
+    ///
+    /// The grid covers the length of the shorter reactor, None if one of both has not been
+    /// simulated.
+    pub fn profile_difference(
+        &self,
+        base: &HaberBoschInstance,
+        steps: usize,
+    ) -> Option<ProfileDifference> {
+        let x_start = |inst: &HaberBoschInstance| inst.results().first()?.x_out.first().copied();
+        let x_end = |inst: &HaberBoschInstance| inst.results().last()?.x_out.last().copied();
+        let start = x_start(self)?.max(x_start(base)?);
+        let end = x_end(self)?.min(x_end(base)?).max(start);
+
+        let nh3 = [profile(base, nh3_fraction), profile(self, nh3_fraction)];
+        let temperature = [profile(base, |y| y[5]), profile(self, |y| y[5])];
+        let diff = |profiles: &[Vec<(f64, f64)>; 2], x| {
+            interpolate(&profiles[1], x) - interpolate(&profiles[0], x)
+        };
+
+        let length: Vec<f64> = (0..steps.max(2))
+            .map(|idx| start + (end - start) * idx as f64 / (steps.max(2) - 1) as f64)
+            .collect();
+        Some(ProfileDifference {
+            nh3: length.iter().map(|x| diff(&nh3, *x)).collect(),
+            temperature: length.iter().map(|x| diff(&temperature, *x)).collect(),
+            length,
+        })
+    }
+
+    /// Final yield, total length and peak temperature of `base` and this instance, None if one
+    /// of both has not been simulated.
+    pub fn metric_deltas(&self, base: &HaberBoschInstance) -> Option<Vec<MetricDelta>> {
+        let (base, other) = (base.summary()?, self.summary()?);
+        Some(vec![
+            MetricDelta::new("Final yield", base.final_yield, other.final_yield),
+            MetricDelta::new("Total length", base.total_length, other.total_length),
+            MetricDelta::new(
+                "Peak temperature [°C]",
+                base.peak_temperature,
+                other.peak_temperature,
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{BedSolverStats, HaberBoschBedResult, TerminationReason};
+    use crate::test_fixtures::kmir;
+    use crate::v2_hints::*;
+
+    /// A state with the given NH3 fraction and temperature.
+    fn state(nh3: f64, temp: f64) -> State {
+        let (n2, h2) = (0.25 * (1. - nh3), 0.75 * (1. - nh3));
+        State::from([n2, h2, nh3, 0., 0., temp, KMIR_REACTOR_PRESSURE])
+    }
+
+    /// A two bed instance with the profiles given as (length, NH3 fraction, temperature).
+    fn with_profiles(beds: [&[(f64, f64, f64)]; 2]) -> HaberBoschInstance {
+        let mut inst = kmir(2);
+        for points in beds {
+            inst.add_next_results(HaberBoschBedResult {
+                x_out: points.iter().map(|(x, _, _)| *x).collect(),
+                y_out: points.iter().map(|(_, nh3, t)| state(*nh3, *t)).collect(),
+                stats: BedSolverStats {
+                    num_eval: 0,
+                    accepted_steps: 0,
+                    rejected_steps: 0,
+                    wall_time: 0.,
+                    termination: TerminationReason::EndOfSpan,
+                },
+                inlet: None,
+            })
+            .unwrap();
+        }
+        inst
+    }
+
+    #[test]
+    fn interpolates_beds_of_different_lengths() {
+        let base = with_profiles([
+            &[(0., 0., 700.), (1., 0.1, 760.)],
+            &[(1., 0.1, 680.), (2., 0.15, 720.)],
+        ]);
+        // the first bed ends later and the second one reaches beyond the base reactor
+        let other = with_profiles([
+            &[(0., 0., 700.), (0.5, 0.05, 730.), (1.5, 0.15, 790.)],
+            &[(1.5, 0.15, 690.), (3., 0.18, 705.)],
+        ]);
+
+        let diff = other.profile_difference(&base, 5).unwrap();
+        assert_eq!(diff.length, [0., 0.5, 1., 1.5, 2.]);
+        // at a bed boundary the inlet of the next bed counts
+        let expected_nh3 = [0., 0., 0., 0.025, 0.01];
+        let expected_temperature = [0., 0., 80., -10., -25.];
+        for idx in 0..5 {
+            assert!((diff.nh3[idx] - expected_nh3[idx]).abs() < 1e-12, "{:?}", diff.nh3);
+            assert!(
+                (diff.temperature[idx] - expected_temperature[idx]).abs() < 1e-9,
+                "{:?}",
+                diff.temperature
+            );
+        }
+    }
+
+    #[test]
+    fn needs_both_scenarios_simulated() {
+        let base = with_profiles([&[(0., 0., 700.), (1., 0.1, 760.)], &[(1., 0.1, 680.)]]);
+        let other = kmir(1);
+        assert!(other.profile_difference(&base, 5).is_none());
+        assert!(base.profile_difference(&other, 5).is_none());
+    }
+}
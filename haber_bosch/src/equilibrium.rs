@@ -0,0 +1,205 @@
+//! Equilibrium and maximum rate curves of the ammonia synthesis.
+//!
+//! Both curves use the rate expression of [`HaberBoschModel`], so they show where the simulated
+//! beds end up: the equilibrium curve is where the rate drops to zero and the maximum rate curve
+//! is the temperature at which a given ammonia fraction is formed fastest. The composition along
+//! the curves follows the stoichiometry of the reaction starting from the feed.
+
+use std::ops::RangeInclusive;
+
+use crate::configuration::{
+    CatalystKinetics, FeedComposition, HaberBoschBedSetup, HaberBoschInstance,
+};
+use crate::simulation::{equilibrium_constant, HaberBoschModel};
+
+/// The gas after the reaction advanced by `extent` mol per mol feed.
+///
+/// Returns the partial pressures of N2, H2 and NH3 and the NH3 mole fraction.
+fn reacted_gas(feed: &FeedComposition, pressure: f64, extent: f64) -> ([f64; 3], f64) {
+    let total = 1. - 2. * extent;
+    let n2 = feed.n2() - extent;
+    let h2 = feed.h2() - 3. * extent;
+    let nh3 = feed.nh3() + 2. * extent;
+    ([n2, h2, nh3].map(|n| n / total * pressure), nh3 / total)
+}
+
+/// The extent of reaction that gives the NH3 mole fraction `nh3`.
+fn extent_of(feed: &FeedComposition, nh3: f64) -> f64 {
+    (nh3 - feed.nh3()) / (2. * (1. + nh3))
+}
+
+/// [-] NH3 mole fraction in equilibrium at `temperature` in K and `pressure` in bar.
+///
+/// `beta` is the bed parameter of the equilibrium constant.
+pub fn equilibrium_nh3_fraction(
+    temperature: f64,
+    pressure: f64,
+    feed: &FeedComposition,
+    beta: f64,
+) -> f64 {
+    let ka2 = equilibrium_constant(temperature, beta).powi(2);
+    // the rate is zero where p_nh3^2 = ka^2 * p_n2 * p_h2^3, which is passed once when all the
+    // ammonia is decomposed at the lower end and N2 or H2 is used up at the upper end
+    let mut lower = -feed.nh3() / 2.;
+    let mut upper = feed.n2().min(feed.h2() / 3.);
+    for _ in 0..100 {
+        let mid = (lower + upper) / 2.;
+        let ([n2, h2, nh3], _) = reacted_gas(feed, pressure, mid);
+        if nh3.powi(2) < ka2 * n2 * h2.powi(3) {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    reacted_gas(feed, pressure, (lower + upper) / 2.).1
+}
+
+/// The equilibrium curve as (temperature in K, NH3 mole fraction) for `steps` temperatures.
+pub fn equilibrium_curve(
+    temperatures: RangeInclusive<f64>,
+    steps: usize,
+    pressure: f64,
+    feed: &FeedComposition,
+    beta: f64,
+) -> Vec<(f64, f64)> {
+    let (start, end) = (*temperatures.start(), *temperatures.end());
+    (0..steps)
+        .map(|idx| {
+            let t = start + (end - start) * idx as f64 / (steps.max(2) - 1) as f64;
+            (t, equilibrium_nh3_fraction(t, pressure, feed, beta))
+        })
+        .collect()
+}
+
+/// The temperature in K with the highest formation rate of NH3 at the mole fraction `nh3`.
+///
+/// The search is limited to the temperature window of the catalyst, None if the rate is not
+/// positive anywhere in it or the maximum lies on the border of the window.
+pub fn max_rate_temperature(
+    nh3: f64,
+    pressure: f64,
+    feed: &FeedComposition,
+    catalyst: &CatalystKinetics,
+    beta: f64,
+) -> Option<f64> {
+    let bed = HaberBoschBedSetup {
+        beta,
+        ..Default::default()
+    };
+    let model = HaberBoschModel::new(pressure, catalyst, bed);
+    let ([n2, h2, p_nh3], _) = reacted_gas(feed, pressure, extent_of(feed, nh3));
+    let rate = |t: f64| model.ammonia_rate(t, n2, h2, p_nh3);
+
+    // golden section search, the rate has a single maximum over the temperature
+    let ratio = (5f64.sqrt() - 1.) / 2.;
+    let (mut a, mut b) = (catalyst.t_min, catalyst.t_max);
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    while b - a > 1e-3 {
+        if rate(c) > rate(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+        c = b - ratio * (b - a);
+        d = a + ratio * (b - a);
+    }
+
+    let t = (a + b) / 2.;
+    let interior = t - catalyst.t_min > 0.01 && catalyst.t_max - t > 0.01;
+    (interior && rate(t) > 0.).then_some(t)
+}
+
+/// The maximum rate curve as (temperature in K, NH3 mole fraction) for `steps` NH3 fractions.
+///
+/// Fractions without a maximum inside the catalyst window are left out.
+pub fn max_rate_curve(
+    nh3_fractions: RangeInclusive<f64>,
+    steps: usize,
+    pressure: f64,
+    feed: &FeedComposition,
+    catalyst: &CatalystKinetics,
+    beta: f64,
+) -> Vec<(f64, f64)> {
+    let (start, end) = (*nh3_fractions.start(), *nh3_fractions.end());
+    (0..steps)
+        .filter_map(|idx| {
+            let nh3 = start + (end - start) * idx as f64 / (steps.max(2) - 1) as f64;
+            max_rate_temperature(nh3, pressure, feed, catalyst, beta).map(|t| (t, nh3))
+        })
+        .collect()
+}
+
+impl HaberBoschInstance {
+    /// The equilibrium curve at the inlet pressure and feed with the `beta` of the first bed.
+    pub fn equilibrium_curve(
+        &self,
+        temperatures: RangeInclusive<f64>,
+        steps: usize,
+    ) -> Vec<(f64, f64)> {
+        let beta = self.beds().first().map_or(0., |bed| bed.beta);
+        equilibrium_curve(temperatures, steps, self.pres(), &self.feed(), beta)
+    }
+
+    /// The maximum rate curve at the inlet pressure and feed with the `beta` of the first bed.
+    pub fn max_rate_curve(
+        &self,
+        nh3_fractions: RangeInclusive<f64>,
+        steps: usize,
+    ) -> Vec<(f64, f64)> {
+        let beta = self.beds().first().map_or(0., |bed| bed.beta);
+        max_rate_curve(
+            nh3_fractions,
+            steps,
+            self.pres(),
+            &self.feed(),
+            self.cat(),
+            beta,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::Catalyst;
+    use crate::v2_hints::*;
+
+    #[test]
+    fn extent_reproduces_the_nh3_fraction() {
+        let feed = FeedComposition::default();
+        for nh3 in [0., 0.05, 0.2, 0.3] {
+            let (_, fraction) = reacted_gas(&feed, KMIR_REACTOR_PRESSURE, extent_of(&feed, nh3));
+            assert!((fraction - nh3).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn equilibrium_fraction_satisfies_the_equilibrium_constant() {
+        let feed = FeedComposition::default();
+        let mut previous = 1.;
+        for t in [650., 700., 750., 800.] {
+            let nh3 = equilibrium_nh3_fraction(t, KMIR_REACTOR_PRESSURE, &feed, KMIR_B1_B);
+            let ([p_n2, p_h2, p_nh3], _) =
+                reacted_gas(&feed, KMIR_REACTOR_PRESSURE, extent_of(&feed, nh3));
+
+            let ka = equilibrium_constant(t, KMIR_B1_B);
+            let ratio = p_nh3 / (p_n2.sqrt() * p_h2.powf(1.5));
+            assert!((ratio / ka - 1.).abs() < 1e-9, "T {}: {} vs {}", t, ratio, ka);
+
+            // the synthesis is exothermic, less ammonia is formed at higher temperatures
+            assert!(nh3 < previous);
+            previous = nh3;
+        }
+    }
+
+    #[test]
+    fn maximum_rate_lies_below_the_equilibrium() {
+        let feed = FeedComposition::default();
+        let catalyst = Catalyst::KMIR.kinetics();
+        let nh3 = 0.12;
+        let t = max_rate_temperature(nh3, KMIR_REACTOR_PRESSURE, &feed, &catalyst, KMIR_B1_B)
+            .unwrap();
+        assert!(equilibrium_nh3_fraction(t, KMIR_REACTOR_PRESSURE, &feed, KMIR_B1_B) > nh3);
+    }
+}
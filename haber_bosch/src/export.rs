@@ -0,0 +1,305 @@
+//! Export of the axial profiles of a simulated [`HaberBoschInstance`].
+//!
+//! Every point of the solver output becomes one row with the bed index, the length, the five gas
+//! components, the temperature and the pressure. CSV is always available, Parquet needs the
+//! `parquet` cargo feature.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use crate::configuration::HaberBoschInstance;
+
+/// How the gas components are written.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CompositionUnit {
+    /// [-] mole fractions like in the plots
+    #[default]
+    MoleFraction,
+    /// [bar] partial pressures at the local pressure
+    PartialPressure,
+}
+
+impl FromStr for CompositionUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "fraction" | "mole-fraction" => Ok(CompositionUnit::MoleFraction),
+            "pressure" | "partial-pressure" => Ok(CompositionUnit::PartialPressure),
+            _ => Err("Unknown composition unit".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Kelvin,
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "c" | "celsius" => Ok(TemperatureUnit::Celsius),
+            "k" | "kelvin" => Ok(TemperatureUnit::Kelvin),
+            _ => Err("Unknown temperature unit".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub composition: CompositionUnit,
+    pub temperature: TemperatureUnit,
+}
+
+impl ExportOptions {
+    /// The column names, the units are part of them.
+    pub fn header(&self) -> Vec<String> {
+        let prefix = match self.composition {
+            CompositionUnit::MoleFraction => "x",
+            CompositionUnit::PartialPressure => "p",
+        };
+        let temperature = match self.temperature {
+            TemperatureUnit::Celsius => "temperature_c",
+            TemperatureUnit::Kelvin => "temperature_k",
+        };
+
+        let mut header = vec!["bed".to_owned(), "length".to_owned()];
+        header.extend(["n2", "h2", "nh3", "ar", "ch4"].map(|comp| format!("{}_{}", prefix, comp)));
+        header.push(temperature.to_owned());
+        header.push("pressure_bar".to_owned());
+        header
+    }
+}
+
+/// One point of the axial profile.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProfileRow {
+    /// the reactor bed, starting at 1
+    pub bed: u32,
+    pub length: f64,
+    /// N2, H2, NH3, Ar and CH4 in the unit of the export options
+    pub components: [f64; 5],
+    pub temperature: f64,
+    /// [bar]
+    pub pressure: f64,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    /// The instance has not been simulated yet.
+    NoResults,
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Parquet(String),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::NoResults => write!(f, "There are no simulation results to export"),
+            ExportError::Io(e) => write!(f, "IO error: {}", e),
+            ExportError::Csv(e) => write!(f, "CSV error: {}", e),
+            ExportError::Parquet(msg) => write!(f, "Parquet error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(e: csv::Error) -> Self {
+        ExportError::Csv(e)
+    }
+}
+
+impl HaberBoschInstance {
+    /// The axial profiles of all beds in the units given by `options`.
+    pub fn profile_rows(&self, options: &ExportOptions) -> Vec<ProfileRow> {
+        let mut rows = vec![];
+        for (bed, res) in self.results().iter().enumerate() {
+            for (x, y) in res.x_out.iter().zip(res.y_out.iter()) {
+                let n_total = y.iter().take(5).sum::<f64>();
+                let mut components = [0.; 5];
+                for (idx, comp) in components.iter_mut().enumerate() {
+                    *comp = match options.composition {
+                        CompositionUnit::MoleFraction => y[idx] / n_total,
+                        // the states are scaled to the inlet pressure, see HaberBoschModel::system
+                        CompositionUnit::PartialPressure => y[idx] * y[6] / self.pres(),
+                    };
+                }
+
+                rows.push(ProfileRow {
+                    bed: bed as u32 + 1,
+                    length: *x,
+                    components,
+                    temperature: match options.temperature {
+                        TemperatureUnit::Celsius => y[5] - 273.,
+                        TemperatureUnit::Kelvin => y[5],
+                    },
+                    pressure: y[6],
+                });
+            }
+        }
+        rows
+    }
+
+    /// Writes the profiles as CSV with a header line.
+    pub fn write_csv<W: std::io::Write>(
+        &self,
+        writer: W,
+        options: &ExportOptions,
+    ) -> Result<(), ExportError> {
+        if self.num_results() == 0 {
+            return Err(ExportError::NoResults);
+        }
+
+        let mut wtr = csv::Writer::from_writer(writer);
+        wtr.write_record(options.header())?;
+        for row in self.profile_rows(options) {
+            let mut record = vec![row.bed.to_string(), row.length.to_string()];
+            record.extend(row.components.iter().map(|c| c.to_string()));
+            record.push(row.temperature.to_string());
+            record.push(row.pressure.to_string());
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn export_csv(
+        &self,
+        path: impl AsRef<Path>,
+        options: &ExportOptions,
+    ) -> Result<(), ExportError> {
+        let file = std::fs::File::create(path)?;
+        self.write_csv(file, options)
+    }
+
+    /// Writes the profiles as a single Parquet row group.
+    #[cfg(feature = "parquet")]
+    pub fn export_parquet(
+        &self,
+        path: impl AsRef<Path>,
+        options: &ExportOptions,
+    ) -> Result<(), ExportError> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt32Array};
+        use arrow_schema::{DataType, Field, Schema};
+        use parquet::arrow::ArrowWriter;
+
+        if self.num_results() == 0 {
+            return Err(ExportError::NoResults);
+        }
+
+        let rows = self.profile_rows(options);
+        let header = options.header();
+        let float_column = |f: &dyn Fn(&ProfileRow) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(f)))
+        };
+
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt32Array::from_iter_values(rows.iter().map(|r| r.bed))),
+            float_column(&|r| r.length),
+        ];
+        for idx in 0..5 {
+            columns.push(float_column(&|r| r.components[idx]));
+        }
+        columns.push(float_column(&|r| r.temperature));
+        columns.push(float_column(&|r| r.pressure));
+
+        let fields: Vec<Field> = header
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let dt = if idx == 0 {
+                    DataType::UInt32
+                } else {
+                    DataType::Float64
+                };
+                Field::new(name, dt, false)
+            })
+            .collect();
+
+        let parquet_err = |e: &dyn Display| ExportError::Parquet(e.to_string());
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(|e| parquet_err(&e))?;
+        let file = std::fs::File::create(path)?;
+        let mut writer =
+            ArrowWriter::try_new(file, batch.schema(), None).map_err(|e| parquet_err(&e))?;
+        writer.write(&batch).map_err(|e| parquet_err(&e))?;
+        writer.close().map_err(|e| parquet_err(&e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{kmir, simulated_kmir};
+    use crate::v2_hints::*;
+
+    fn csv_records(inst: &HaberBoschInstance, options: &ExportOptions) -> Vec<Vec<f64>> {
+        let mut buffer = vec![];
+        inst.write_csv(&mut buffer, options).unwrap();
+
+        let mut rdr = csv::Reader::from_reader(buffer.as_slice());
+        assert_eq!(rdr.headers().unwrap(), options.header().as_slice());
+        rdr.records()
+            .map(|rec| rec.unwrap().iter().map(|v| v.parse().unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_row_per_output_point() {
+        let inst = simulated_kmir(2);
+        let records = csv_records(&inst, &ExportOptions::default());
+
+        let points: usize = inst.results().iter().map(|res| res.x_out.len()).sum();
+        assert_eq!(records.len(), points);
+        assert_eq!(records[0].len(), 9);
+        assert_eq!(records.first().unwrap()[0], 1.);
+        assert_eq!(records.last().unwrap()[0], 2.);
+        // the rows follow the reactor, the length never decreases
+        assert!(records.windows(2).all(|pair| pair[0][1] <= pair[1][1]));
+        for rec in &records {
+            assert!((rec[2..7].iter().sum::<f64>() - 1.).abs() < 1e-9);
+            assert_eq!(rec[8], KMIR_REACTOR_PRESSURE);
+        }
+    }
+
+    #[test]
+    fn converts_the_units() {
+        let inst = simulated_kmir(2);
+        let options = ExportOptions {
+            composition: CompositionUnit::PartialPressure,
+            temperature: TemperatureUnit::Kelvin,
+        };
+        assert_eq!(options.header()[2], "p_n2");
+        assert_eq!(options.header()[7], "temperature_k");
+
+        let kelvin = csv_records(&inst, &options);
+        let celsius = csv_records(&inst, &ExportOptions::default());
+        assert!((kelvin[0][7] - KMIR_B1_TS).abs() < 1e-9);
+        assert!((kelvin[0][7] - celsius[0][7] - 273.).abs() < 1e-9);
+        // the feed adds up to the inlet pressure
+        let p_feed = kelvin[0][2..7].iter().sum::<f64>();
+        assert!((p_feed - KMIR_REACTOR_PRESSURE).abs() < FEED_SUM_TOLERANCE * p_feed);
+    }
+
+    #[test]
+    fn refuses_to_export_without_results() {
+        let res = kmir(2).write_csv(vec![], &ExportOptions::default());
+        assert!(matches!(res, Err(ExportError::NoResults)));
+    }
+}
@@ -1,4 +1,5 @@
 #![allow(unused)]
+#![allow(clippy::assign_op_pattern)] // the long form is easier to read for beginners
 use std::fmt::Display; // also this gives a warning we need it after uncommenting some lines
 
 // hint: this uses all the hints given for the task in a central place:
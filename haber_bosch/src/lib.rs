@@ -1,9 +1,23 @@
 use wasm_bindgen::prelude::wasm_bindgen;
 
-mod configuration;
-mod simulation;
+pub mod batch;
+pub mod chart;
+pub mod config;
+pub mod configuration;
+pub mod diagnostics;
+pub mod difference;
+pub mod equilibrium;
+pub mod export;
+pub mod optimization;
+pub mod recycle;
+mod rosenbrock;
+pub mod simulation;
+pub mod summary;
+pub mod sweep;
+#[cfg(test)]
+mod test_fixtures;
 mod v2_hints;
-mod visualization;
+pub mod visualization;
 
 pub mod web;
 pub mod web_range;
@@ -19,7 +33,7 @@ extern "C" {
 macro_rules! console_log {
     // Note that this is using the `log` function imported above during
     // `bare_bones`
-    ($($t:tt)*) => (crate::log(&format_args!($($t)*).to_string()))
+    ($($t:tt)*) => ($crate::log(&format_args!($($t)*).to_string()))
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,621 @@
+//! Search for the bed temperatures with the best ammonia yield.
+//!
+//! This automates what the course does by hand in the web tool: the `t_start` and optionally the
+//! `t_max` of every bed are varied until the final yield, or the yield per reactor length, is as
+//! high as possible. The search is a Nelder-Mead simplex in the unit cube, every corner is mapped
+//! to temperatures inside the catalyst window and below the maximum temperature, so all points
+//! respect the bounds. A maximum total length is enforced by a penalty on the objective.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use serde::Serialize;
+
+use crate::config::{ConfigError, ReactorConfig};
+use crate::configuration::{
+    is_positive, CatalystKinetics, HaberBoschInstance, HaberBoschInstanceBuilder,
+};
+use crate::export::ExportError;
+use crate::simulation::sequential_simulation;
+use crate::v2_hints::*;
+
+/// What is maximized.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+pub enum Objective {
+    /// [-] ammonia mole fraction at the outlet of the last bed
+    #[default]
+    FinalYield,
+    /// [1/m] final yield divided by the total reactor length
+    YieldPerLength,
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "yield" | "final-yield" => Ok(Objective::FinalYield),
+            "yield-per-length" | "yield/length" => Ok(Objective::YieldPerLength),
+            _ => Err("Unknown objective".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OptimizationError {
+    /// The base configuration or the bounds are invalid.
+    Config(ConfigError),
+    /// None of the evaluated points could be simulated.
+    NoFeasiblePoint { evaluations: usize },
+}
+
+impl Display for OptimizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptimizationError::Config(e) => write!(f, "{}", e),
+            OptimizationError::NoFeasiblePoint { evaluations } => write!(
+                f,
+                "None of the {} evaluated points could be simulated",
+                evaluations
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OptimizationError {}
+
+impl From<ConfigError> for OptimizationError {
+    fn from(e: ConfigError) -> Self {
+        OptimizationError::Config(e)
+    }
+}
+
+/// The bed temperatures to optimize and the constraints of the search.
+#[derive(Debug, Clone)]
+pub struct OptimizationProblem {
+    /// catalyst, pressure and the beds, the temperatures of the beds are the start point
+    pub base: ReactorConfig,
+    pub objective: Objective,
+    /// [K] no bed gets hotter than this, the catalyst window may limit it further
+    pub max_temperature: f64,
+    /// points with a longer reactor are penalized
+    pub max_total_length: Option<f64>,
+    /// also vary `t_max` of the beds, otherwise it is only limited to the bounds
+    pub optimize_t_max: bool,
+    /// no more points are simulated once this many have been evaluated
+    pub max_evaluations: usize,
+    /// the search stops when the objective values of the simplex differ less than this
+    pub tolerance: f64,
+}
+
+/// One evaluated point of the search, temperatures are given in Kelvin.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptimizationStep {
+    pub evaluation: usize,
+    pub t_start: Vec<f64>,
+    pub t_max: Vec<f64>,
+    /// None if the point could not be simulated
+    pub total_length: Option<f64>,
+    pub final_yield: Option<f64>,
+    /// including the length penalty, -inf if the point could not be simulated
+    pub objective: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationResult {
+    /// the simulated reactor with the best bed temperatures
+    pub instance: HaberBoschInstance,
+    pub config: ReactorConfig,
+    pub objective: f64,
+    pub evaluations: usize,
+    /// false if the search stopped because of the evaluation limit
+    pub converged: bool,
+    /// the temperatures of the optimum that sit on a bound of the search, widening that bound
+    /// may improve the objective further
+    pub active_bounds: Vec<ActiveBound>,
+    /// every evaluated point in the order of evaluation
+    pub history: Vec<OptimizationStep>,
+}
+
+/// A temperature of the optimum that lies on a bound of the search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActiveBound {
+    /// e.g. `beds[1].t_start`
+    pub field: String,
+    /// [K]
+    pub temperature: f64,
+    /// true for the upper bound
+    pub upper: bool,
+}
+
+impl Display for ActiveBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at its {} bound {:.1} °C",
+            self.field,
+            if self.upper { "upper" } else { "lower" },
+            self.temperature - 273.
+        )
+    }
+}
+
+impl OptimizationResult {
+    /// Writes the history as CSV, one row per evaluation with the temperatures of each bed.
+    pub fn write_history_csv<W: std::io::Write>(&self, writer: W) -> Result<(), ExportError> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        let mut header = vec!["evaluation".to_owned()];
+        for idx in 1..=self.config.beds.len() {
+            header.push(format!("t_start[{}]", idx));
+            header.push(format!("t_max[{}]", idx));
+        }
+        header.extend(["total_length", "final_yield", "objective"].map(str::to_owned));
+        wtr.write_record(&header)?;
+
+        let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        for step in &self.history {
+            let mut record = vec![step.evaluation.to_string()];
+            for (t_start, t_max) in step.t_start.iter().zip(&step.t_max) {
+                record.push(t_start.to_string());
+                record.push(t_max.to_string());
+            }
+            record.push(optional(step.total_length));
+            record.push(optional(step.final_yield));
+            record.push(step.objective.to_string());
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn export_history_csv(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let file = std::fs::File::create(path)?;
+        self.write_history_csv(file)
+    }
+}
+
+impl Display for OptimizationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Optimization {} after {} evaluations, objective {:.6}",
+            match (self.converged, self.active_bounds.is_empty()) {
+                (true, true) => "converged",
+                (true, false) => "converged on a bound",
+                (false, _) => "stopped",
+            },
+            self.evaluations,
+            self.objective
+        )?;
+        for bound in &self.active_bounds {
+            writeln!(f, "{}, the optimum may lie beyond it", bound)?;
+        }
+        for (idx, bed) in self.config.beds.iter().enumerate() {
+            writeln!(
+                f,
+                "Bed {}: t_start {:.1} °C, t_max {:.1} °C",
+                idx + 1,
+                bed.t_start - 273.,
+                bed.t_max - 273.
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl OptimizationProblem {
+    pub fn new(base: ReactorConfig) -> Self {
+        OptimizationProblem {
+            base,
+            objective: Objective::FinalYield,
+            max_temperature: f64::INFINITY,
+            max_total_length: None,
+            optimize_t_max: true,
+            max_evaluations: OPTIMIZER_MAX_EVALUATIONS,
+            tolerance: OPTIMIZER_TOLERANCE,
+        }
+    }
+
+    /// A problem with `beds` equal beds, at least one, that start in the middle of the catalyst
+    /// window.
+    pub fn for_catalyst(
+        catalyst: impl Into<CatalystKinetics>,
+        pressure: f64,
+        beds: usize,
+    ) -> Result<Self, OptimizationError> {
+        let catalyst = catalyst.into();
+        let name = format!("HB_{}", catalyst.name);
+        let t_start = (catalyst.t_min + catalyst.t_max) / 2.;
+        let t_max = catalyst.t_max;
+
+        let mut builder = HaberBoschInstanceBuilder::create(pressure, catalyst);
+        for _ in 0..beds.max(1) {
+            builder = builder.add_bed(t_start, OPTIMIZER_BED_T_SLOPE, t_max, OPTIMIZER_BED_BETA);
+        }
+        let inst = builder.build().map_err(ConfigError::from)?;
+        Ok(Self::new(ReactorConfig {
+            name: Some(name),
+            ..ReactorConfig::from_instance(&inst)
+        }))
+    }
+
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    pub fn with_max_temperature(mut self, max_temperature: f64) -> Self {
+        self.max_temperature = max_temperature;
+        self
+    }
+
+    pub fn with_max_total_length(mut self, max_total_length: Option<f64>) -> Self {
+        self.max_total_length = max_total_length;
+        self
+    }
+
+    pub fn with_optimize_t_max(mut self, optimize_t_max: bool) -> Self {
+        self.optimize_t_max = optimize_t_max;
+        self
+    }
+
+    pub fn with_max_evaluations(mut self, max_evaluations: usize) -> Self {
+        self.max_evaluations = max_evaluations;
+        self
+    }
+
+    /// Runs the search, the beds of the base configuration are the start point.
+    pub fn optimize(&self) -> Result<OptimizationResult, OptimizationError> {
+        self.base.build()?;
+        let bounds = self.bounds()?;
+
+        let mut search = Search {
+            problem: self,
+            bounds,
+            history: vec![],
+        };
+        let start = search.start_point();
+        let (best, converged) = nelder_mead(
+            start,
+            self.tolerance,
+            self.max_evaluations,
+            |point| search.evaluate(point),
+        );
+
+        let config = search.config_at(&best);
+        let active_bounds = search.active_bounds(&best);
+        let evaluations = search.history.len();
+        let mut instance = config.build()?;
+        let objective = match sequential_simulation(&mut instance, false) {
+            Ok(()) => search.objective_of(&instance),
+            Err(_) => f64::NEG_INFINITY,
+        };
+        if objective == f64::NEG_INFINITY {
+            return Err(OptimizationError::NoFeasiblePoint { evaluations });
+        }
+
+        Ok(OptimizationResult {
+            instance,
+            config,
+            objective,
+            evaluations,
+            converged,
+            active_bounds,
+            history: search.history,
+        })
+    }
+
+    /// The lowest and highest temperature a bed may have.
+    fn bounds(&self) -> Result<(f64, f64), ConfigError> {
+        let catalyst = self.base.catalyst_kinetics()?;
+        let upper = self.max_temperature.min(catalyst.t_max);
+        if !is_positive(upper - OPTIMIZER_MIN_RAMP - catalyst.t_min) {
+            return Err(ConfigError::Invalid {
+                field: "max_temperature".to_owned(),
+                message: format!(
+                    "has to be above the lower end of the catalyst window {} K",
+                    catalyst.t_min
+                ),
+            });
+        }
+        Ok((catalyst.t_min, upper))
+    }
+
+    /// Number of variables per bed.
+    fn variables_per_bed(&self) -> usize {
+        if self.optimize_t_max {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// The state of a running search.
+struct Search<'a> {
+    problem: &'a OptimizationProblem,
+    bounds: (f64, f64),
+    history: Vec<OptimizationStep>,
+}
+
+impl Search<'_> {
+    /// The bed temperatures of the base configuration in the unit cube.
+    fn start_point(&self) -> Vec<f64> {
+        let (lower, upper) = self.bounds;
+        let mut point = vec![];
+        for bed in &self.problem.base.beds {
+            let t_start = bed.t_start.clamp(lower, upper - OPTIMIZER_MIN_RAMP);
+            point.push((t_start - lower) / (upper - OPTIMIZER_MIN_RAMP - lower));
+            if self.problem.optimize_t_max {
+                let span = upper - t_start - OPTIMIZER_MIN_RAMP;
+                let u = (bed.t_max - t_start - OPTIMIZER_MIN_RAMP) / span;
+                point.push(if span > 0. { u.clamp(0., 1.) } else { 0. });
+            }
+        }
+        point
+    }
+
+    /// The base configuration with the temperatures of a point in the unit cube.
+    fn config_at(&self, point: &[f64]) -> ReactorConfig {
+        let (lower, upper) = self.bounds;
+        let mut config = self.problem.base.clone();
+        for (bed, vars) in config
+            .beds
+            .iter_mut()
+            .zip(point.chunks(self.problem.variables_per_bed()))
+        {
+            bed.t_start = lower + vars[0] * (upper - OPTIMIZER_MIN_RAMP - lower);
+            let min_t_max = bed.t_start + OPTIMIZER_MIN_RAMP;
+            bed.t_max = match vars.get(1) {
+                Some(u) => min_t_max + u * (upper - min_t_max),
+                None => bed.t_max.clamp(min_t_max, upper),
+            };
+        }
+        config
+    }
+
+    /// The variables of a point in the unit cube that lie on the edge of the cube.
+    fn active_bounds(&self, point: &[f64]) -> Vec<ActiveBound> {
+        let config = self.config_at(point);
+        let mut bounds = vec![];
+        let vars_per_bed = point.chunks(self.problem.variables_per_bed());
+        for (idx, (bed, vars)) in config.beds.iter().zip(vars_per_bed).enumerate() {
+            let temperatures = [("t_start", bed.t_start), ("t_max", bed.t_max)];
+            for (u, (name, temperature)) in vars.iter().zip(temperatures) {
+                if *u <= OPTIMIZER_BOUND_TOLERANCE || *u >= 1. - OPTIMIZER_BOUND_TOLERANCE {
+                    bounds.push(ActiveBound {
+                        field: format!("beds[{}].{}", idx + 1, name),
+                        temperature,
+                        upper: *u > 0.5,
+                    });
+                }
+            }
+        }
+        bounds
+    }
+
+    fn objective_of(&self, inst: &HaberBoschInstance) -> f64 {
+        let Some(summary) = inst.summary() else {
+            return f64::NEG_INFINITY;
+        };
+        let value = match self.problem.objective {
+            Objective::FinalYield => summary.final_yield,
+            Objective::YieldPerLength => summary.final_yield / summary.total_length,
+        };
+        let excess = self
+            .problem
+            .max_total_length
+            .map_or(0., |max| (summary.total_length - max).max(0.));
+        value - OPTIMIZER_LENGTH_PENALTY * excess
+    }
+
+    /// Simulates a point, it is projected into the unit cube first. Returns the value that is
+    /// minimized, the negative objective.
+    fn evaluate(&mut self, point: &mut [f64]) -> f64 {
+        for x in point.iter_mut() {
+            *x = x.clamp(0., 1.);
+        }
+        let config = self.config_at(point);
+
+        let simulated = config.build().ok().and_then(|mut inst| {
+            sequential_simulation(&mut inst, false).ok()?;
+            Some(inst)
+        });
+        let summary = simulated.as_ref().and_then(|inst| inst.summary());
+        let objective = simulated
+            .as_ref()
+            .map_or(f64::NEG_INFINITY, |inst| self.objective_of(inst));
+
+        self.history.push(OptimizationStep {
+            evaluation: self.history.len() + 1,
+            t_start: config.beds.iter().map(|bed| bed.t_start).collect(),
+            t_max: config.beds.iter().map(|bed| bed.t_max).collect(),
+            total_length: summary.as_ref().map(|s| s.total_length),
+            final_yield: summary.as_ref().map(|s| s.final_yield),
+            objective,
+        });
+        -objective
+    }
+}
+
+/// Minimizes `f` with a Nelder-Mead simplex that starts at `start`, returns the best point and
+/// whether the values of the simplex came closer than `tolerance`.
+///
+/// `f` is called at most `max_evaluations` times, the search stops with the best point so far
+/// when the budget is used up.
+fn nelder_mead<F>(
+    start: Vec<f64>,
+    tolerance: f64,
+    max_evaluations: usize,
+    mut f: F,
+) -> (Vec<f64>, bool)
+where
+    F: FnMut(&mut [f64]) -> f64,
+{
+    const REFLECTION: f64 = 1.;
+    const EXPANSION: f64 = 2.;
+    const CONTRACTION: f64 = 0.5;
+    const SHRINK: f64 = 0.5;
+
+    let mut evaluations = 0;
+    let mut evaluate = |point: &mut [f64]| {
+        if evaluations >= max_evaluations {
+            return None;
+        }
+        evaluations += 1;
+        Some(f(point))
+    };
+    let best_of = |simplex: &[(Vec<f64>, f64)], start: &[f64]| {
+        simplex
+            .iter()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or_else(|| start.to_vec(), |(point, _)| point.clone())
+    };
+
+    let n = start.len();
+    let mut simplex = vec![];
+    for idx in 0..=n {
+        let mut point = start.clone();
+        if idx > 0 {
+            // step away from the bounds if the start point lies on one
+            let x = &mut point[idx - 1];
+            *x += if *x + OPTIMIZER_INITIAL_STEP <= 1. {
+                OPTIMIZER_INITIAL_STEP
+            } else {
+                -OPTIMIZER_INITIAL_STEP
+            };
+        }
+        let Some(value) = evaluate(&mut point) else {
+            return (best_of(&simplex, &start), false);
+        };
+        simplex.push((point, value));
+    }
+
+    loop {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        if (worst - best).abs() <= tolerance {
+            return (simplex.swap_remove(0).0, true);
+        }
+
+        let mut centroid = vec![0.; n];
+        for (point, _) in &simplex[..n] {
+            for (c, x) in centroid.iter_mut().zip(point) {
+                *c += x / n as f64;
+            }
+        }
+        let towards = |from: &[f64], factor: f64| -> Vec<f64> {
+            centroid
+                .iter()
+                .zip(from)
+                .map(|(c, x)| c + factor * (x - c))
+                .collect()
+        };
+
+        let mut reflected = towards(&simplex[n].0, -REFLECTION);
+        let Some(f_reflected) = evaluate(&mut reflected) else {
+            return (best_of(&simplex, &start), false);
+        };
+
+        if f_reflected < best {
+            let mut expanded = towards(&reflected, EXPANSION);
+            simplex[n] = match evaluate(&mut expanded) {
+                Some(f_expanded) if f_expanded < f_reflected => (expanded, f_expanded),
+                _ => (reflected, f_reflected),
+            };
+        } else if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+        } else {
+            let (mut contracted, limit) = if f_reflected < worst {
+                (towards(&reflected, CONTRACTION), f_reflected)
+            } else {
+                (towards(&simplex[n].0, CONTRACTION), worst)
+            };
+            let Some(f_contracted) = evaluate(&mut contracted) else {
+                return (best_of(&simplex, &start), false);
+            };
+            if f_contracted < limit {
+                simplex[n] = (contracted, f_contracted);
+            } else {
+                let best_point = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    let mut point: Vec<f64> = vertex
+                        .0
+                        .iter()
+                        .zip(&best_point)
+                        .map(|(x, b)| b + SHRINK * (x - b))
+                        .collect();
+                    let Some(value) = evaluate(&mut point) else {
+                        return (best_of(&simplex, &start), false);
+                    };
+                    *vertex = (point, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::Catalyst;
+
+    /// Smooth bowl with its minimum inside the unit square.
+    fn bowl(point: &mut [f64]) -> f64 {
+        (point[0] - 0.3).powi(2) + 2. * (point[1] - 0.7).powi(2)
+    }
+
+    #[test]
+    fn finds_an_interior_optimum() {
+        let (best, converged) = nelder_mead(vec![0.5, 0.5], 1e-12, 500, bowl);
+        assert!(converged);
+        assert!((best[0] - 0.3).abs() < 1e-3 && (best[1] - 0.7).abs() < 1e-3);
+    }
+
+    #[test]
+    fn never_exceeds_the_evaluation_budget() {
+        for max_evaluations in 0..40 {
+            let mut calls = 0;
+            let (best, converged) = nelder_mead(vec![0.5, 0.5], 1e-12, max_evaluations, |p| {
+                calls += 1;
+                bowl(p)
+            });
+            assert_eq!(calls, max_evaluations);
+            assert!(!converged);
+            assert_eq!(best.len(), 2);
+        }
+    }
+
+    #[test]
+    fn reports_the_evaluations_of_the_reactor_search() {
+        let problem = OptimizationProblem::for_catalyst(Catalyst::KMIR, KMIR_REACTOR_PRESSURE, 2)
+            .unwrap()
+            .with_max_evaluations(12);
+        let result = problem.optimize().unwrap();
+        assert_eq!(result.evaluations, 12);
+        assert_eq!(result.history.len(), 12);
+        assert!(!result.converged);
+    }
+
+    #[test]
+    fn detects_temperatures_on_the_bounds() {
+        let problem =
+            OptimizationProblem::for_catalyst(Catalyst::KMIR, KMIR_REACTOR_PRESSURE, 2).unwrap();
+        let bounds = problem.bounds().unwrap();
+        let search = Search {
+            problem: &problem,
+            bounds,
+            history: vec![],
+        };
+
+        let active = search.active_bounds(&[0., 0.5, 0.4, 1.]);
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[0].field, "beds[1].t_start");
+        assert!(!active[0].upper);
+        assert_eq!(active[0].temperature, bounds.0);
+        assert_eq!(active[1].field, "beds[2].t_max");
+        assert!(active[1].upper);
+        assert_eq!(active[1].temperature, bounds.1);
+
+        assert!(search.active_bounds(&[0.2, 0.5, 0.4, 0.6]).is_empty());
+    }
+}
@@ -0,0 +1,338 @@
+//! Simulation of the synthesis loop around the converter.
+//!
+//! The gas leaving the last bed is cooled down and the condensed ammonia is removed in a
+//! separator. A small part of the remaining gas is purged to keep argon and methane from building
+//! up, the rest is mixed with fresh makeup gas and fed back into the converter. The loop is solved
+//! by successive substitution of the converter feed, accelerated with the bounded Wegstein method,
+//! until its composition does not change anymore.
+
+use std::fmt::Display;
+
+use wasm_bindgen::prelude::*;
+
+use crate::configuration::{
+    is_positive, ConfigurationError, FeedComposition, HaberBoschInstance,
+};
+use crate::simulation::{sequential_simulation, SimulationError};
+use crate::v2_hints::*;
+
+/// Settings of the separator, the purge and the makeup gas of the loop.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone)]
+pub struct RecycleSettings {
+    /// [-] fraction of the ammonia leaving the converter that is condensed in the separator
+    pub nh3_recovery: f64,
+    /// [-] fraction of the separator gas that leaves the loop as purge
+    pub purge_fraction: f64,
+    /// [mol/s] fresh syngas fed into the loop
+    pub makeup_flow: f64,
+    /// [-] mole fractions of the makeup gas (N2, H2, NH3, Ar, CH4)
+    #[wasm_bindgen(skip)]
+    pub makeup: [f64; 5],
+    /// [-] convergence limit of the converter feed mole fractions, its flow relative to the
+    /// makeup flow and the quench ratio
+    pub tolerance: f64,
+    pub max_iterations: u32,
+}
+
+#[wasm_bindgen]
+impl RecycleSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for RecycleSettings {
+    fn default() -> Self {
+        Self {
+            nh3_recovery: SEPARATOR_NH3_RECOVERY,
+            purge_fraction: PURGE_FRACTION,
+            makeup_flow: MAKEUP_MOLAR_FLOW,
+            makeup: MAKEUP_MOLE_FRACTIONS,
+            tolerance: RECYCLE_TOLERANCE,
+            max_iterations: RECYCLE_MAX_ITERATIONS,
+        }
+    }
+}
+
+/// Errors of the loop simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecycleError {
+    /// The separator recovery or the purge fraction is outside of its valid range.
+    InvalidSettings(String),
+    /// The converter simulation failed in the given iteration.
+    Simulation { iteration: u32, error: SimulationError },
+    /// The mixed converter feed is not a valid feed composition.
+    Configuration { iteration: u32, error: ConfigurationError },
+    /// The converter feed did not converge within the maximum number of iterations.
+    NotConverged { iterations: u32, residual: f64 },
+}
+
+impl Display for RecycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecycleError::InvalidSettings(msg) => write!(f, "Invalid recycle settings: {}", msg),
+            RecycleError::Simulation { iteration, error } => {
+                write!(f, "Loop iteration {}: {}", iteration, error)
+            }
+            RecycleError::Configuration { iteration, error } => {
+                write!(f, "Loop iteration {}: {}", iteration, error)
+            }
+            RecycleError::NotConverged { iterations, residual } => write!(
+                f,
+                "Loop did not converge within {} iterations, residual {:e}",
+                iterations, residual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecycleError {}
+
+/// One bounded Wegstein step from the unknowns of the last two passes, each given as
+/// (unknowns, their update by one pass through the loop).
+///
+/// The unknowns are coupled by the loop, so they share one slope that is estimated from the
+/// change of all of them. The acceleration factor is bounded to keep the step from overshooting,
+/// for a factor of zero this is successive substitution.
+fn wegstein(previous: ([f64; 7], [f64; 7]), current: ([f64; 7], [f64; 7])) -> [f64; 7] {
+    let distance = |a: &[f64; 7], b: &[f64; 7]| {
+        a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>().sqrt()
+    };
+    let slope = distance(&current.1, &previous.1) / distance(&current.0, &previous.0);
+    if !slope.is_finite() || slope >= 1. {
+        return current.1;
+    }
+
+    let q = (slope / (slope - 1.)).clamp(WEGSTEIN_MIN_FACTOR, WEGSTEIN_MAX_FACTOR);
+    let mut next = current.1;
+    for (idx, value) in next.iter_mut().enumerate() {
+        *value = q * current.0[idx] + (1. - q) * current.1[idx];
+    }
+    next
+}
+
+/// The converged state of the loop, all flows are in [mol/s].
+#[derive(Debug, Clone)]
+pub struct RecycleResult {
+    /// the converter simulated with the converged feed
+    pub converter: HaberBoschInstance,
+    pub iterations: u32,
+    /// flow entering the converter, makeup plus recycle, including the quench gas
+    pub converter_flow: f64,
+    pub recycle_flow: f64,
+    /// liquid ammonia leaving the separator
+    pub ammonia_product: f64,
+    /// component flows leaving the loop with the purge gas (N2, H2, NH3, Ar, CH4)
+    pub purge: [f64; 5],
+    /// [-] fraction of the makeup N2 and H2 that is converted to ammonia
+    pub overall_conversion: f64,
+    /// [-] fraction of the makeup N2 and H2 that is lost with the purge
+    pub purge_loss: f64,
+    /// [-] mole fraction of argon and methane at the converter inlet
+    pub inert_level: f64,
+}
+
+impl Display for RecycleResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Recycle loop converged after {} iterations", self.iterations)?;
+        writeln!(
+            f,
+            "Converter Feed: {:.1} mol/s ({:.1} recycle), inerts {:.4}",
+            self.converter_flow, self.recycle_flow, self.inert_level
+        )?;
+        writeln!(f, "Ammonia Product: {:.1} mol/s", self.ammonia_product)?;
+        writeln!(
+            f,
+            "Purge: {:.2} mol/s, N2/H2 losses {:.4}",
+            self.purge.iter().sum::<f64>(),
+            self.purge_loss
+        )?;
+        write!(f, "Overall Conversion: {:.4}", self.overall_conversion)
+    }
+}
+
+/// Solves the synthesis loop around the converter `inst`.
+///
+/// The feed composition and flow of `inst` are only used as start values for the first pass.
+pub fn recycle_simulation(
+    inst: &HaberBoschInstance,
+    settings: &RecycleSettings,
+) -> Result<RecycleResult, RecycleError> {
+    if !(0. ..=1.).contains(&settings.nh3_recovery) {
+        return Err(RecycleError::InvalidSettings(format!(
+            "NH3 recovery has to be in [0, 1], got {}",
+            settings.nh3_recovery
+        )));
+    }
+    if !(0. ..=1.).contains(&settings.purge_fraction) || !is_positive(settings.makeup_flow) {
+        return Err(RecycleError::InvalidSettings(format!(
+            "purge fraction {} or makeup flow {} out of range",
+            settings.purge_fraction, settings.makeup_flow
+        )));
+    }
+
+    let [n2, h2, nh3, ar, ch4] = settings.makeup;
+    FeedComposition::new(n2, h2, nh3, ar, ch4)
+        .map_err(|error| RecycleError::InvalidSettings(format!("makeup gas: {}", error)))?;
+
+    let makeup = settings.makeup.map(|frac| frac * settings.makeup_flow);
+    let mut feed = inst.feed();
+    let mut converter_flow = inst.feed_flow();
+    let mut quench_ratio = 0.;
+    let mut residual = f64::INFINITY;
+    // the unknowns in comparable magnitudes: the feed mole fractions, the converter flow
+    // relative to the makeup flow and the quench ratio
+    let unknowns_of = |fractions: [f64; 5], flow: f64, quench_ratio: f64| {
+        let mut unknowns = [0.; 7];
+        unknowns[..5].copy_from_slice(&fractions);
+        unknowns[5] = flow / settings.makeup_flow;
+        unknowns[6] = quench_ratio;
+        unknowns
+    };
+    // the last pass before the current one, (unknowns, their update)
+    let mut previous: Option<([f64; 7], [f64; 7])> = None;
+
+    for iteration in 1..=settings.max_iterations {
+        // the quench gas is split off the converter feed, the first bed only gets the rest
+        let mut converter = inst.with_feed(feed, converter_flow / (1. + quench_ratio));
+        sequential_simulation(&mut converter, false)
+            .map_err(|error| RecycleError::Simulation { iteration, error })?;
+        let outlet = converter.outlet_flows().unwrap();
+        let next_quench_ratio = converter
+            .inter_bed_results()
+            .iter()
+            .map(|unit| unit.quench_ratio)
+            .sum::<f64>();
+
+        // separator: only ammonia condenses, then the purge splits off the gas
+        let mut gas = outlet;
+        let ammonia_product = outlet[2] * settings.nh3_recovery;
+        gas[2] -= ammonia_product;
+        let purge = gas.map(|flow| flow * settings.purge_fraction);
+
+        let mut next = [0.; 5];
+        for idx in 0..5 {
+            next[idx] = gas[idx] - purge[idx] + makeup[idx];
+        }
+        let next_flow = next.iter().sum::<f64>();
+        let recycle_flow = next_flow - settings.makeup_flow;
+
+        let unknowns = unknowns_of(feed.as_array(), converter_flow, quench_ratio);
+        let update = unknowns_of(next.map(|flow| flow / next_flow), next_flow, next_quench_ratio);
+        residual = unknowns
+            .iter()
+            .zip(update)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0., f64::max);
+
+        if residual < settings.tolerance {
+            let syngas = makeup[0] + makeup[1];
+            // every mole of ammonia leaving the loop consumes two moles of N2 and H2
+            let produced = ammonia_product + purge[2] - makeup[2];
+            return Ok(RecycleResult {
+                iterations: iteration,
+                converter_flow,
+                recycle_flow,
+                ammonia_product,
+                purge,
+                overall_conversion: 2. * produced / syngas,
+                purge_loss: (purge[0] + purge[1]) / syngas,
+                inert_level: feed.inerts(),
+                converter,
+            });
+        }
+
+        let accelerated = match previous {
+            Some(previous) if iteration % WEGSTEIN_INTERVAL == 0 => {
+                wegstein(previous, (unknowns, update))
+            }
+            _ => update,
+        };
+        previous = Some((unknowns, update));
+
+        let mut fractions = [0.; 5];
+        for (frac, value) in fractions.iter_mut().zip(accelerated) {
+            *frac = value.max(0.);
+        }
+        let sum = fractions.iter().sum::<f64>();
+        let [n2, h2, nh3, ar, ch4] = fractions.map(|frac| frac / sum);
+        feed = FeedComposition::new(n2, h2, nh3, ar, ch4)
+            .map_err(|error| RecycleError::Configuration { iteration, error })?;
+        // the converter gets at least the makeup gas
+        converter_flow = accelerated[5].max(1.) * settings.makeup_flow;
+        quench_ratio = accelerated[6].max(0.);
+    }
+
+    Err(RecycleError::NotConverged {
+        iterations: settings.max_iterations,
+        residual,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::kmir;
+
+    #[test]
+    fn rejects_invalid_makeup_gas() {
+        for makeup in [[0.25, 0.8, 0., -0.05, 0.], [0.25, 0.7, 0., 0., 0.]] {
+            let settings = RecycleSettings {
+                makeup,
+                ..Default::default()
+            };
+            assert!(matches!(
+                recycle_simulation(&kmir(2), &settings),
+                Err(RecycleError::InvalidSettings(_))
+            ));
+        }
+
+        for makeup_flow in [0., -1., f64::NAN, f64::INFINITY] {
+            let settings = RecycleSettings {
+                makeup_flow,
+                ..Default::default()
+            };
+            assert!(matches!(
+                recycle_simulation(&kmir(2), &settings),
+                Err(RecycleError::InvalidSettings(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn balances_the_loop_at_convergence() {
+        let settings = RecycleSettings::default();
+        let result = recycle_simulation(&kmir(2), &settings).unwrap();
+        assert!(result.iterations < settings.max_iterations / 2);
+
+        // makeup plus the reaction in the converter leaves the loop as product and purge
+        let feed = result.converter.feed().as_array();
+        let outlet = result.converter.outlet_flows().unwrap();
+        for idx in 0..5 {
+            let makeup = settings.makeup[idx] * settings.makeup_flow;
+            let reaction = outlet[idx] - feed[idx] * result.converter_flow;
+            let product = if idx == 2 { result.ammonia_product } else { 0. };
+            let error = makeup + reaction - product - result.purge[idx];
+            assert!(error.abs() < 1e-4 * settings.makeup_flow, "component {}: {}", idx, error);
+        }
+
+        // the inerts only leave with the purge
+        for idx in [3, 4] {
+            let makeup = settings.makeup[idx] * settings.makeup_flow;
+            assert!((result.purge[idx] - makeup).abs() < 1e-4 * makeup);
+        }
+    }
+
+    #[test]
+    fn accepts_a_complete_ammonia_recovery() {
+        let settings = RecycleSettings {
+            nh3_recovery: 1.,
+            ..Default::default()
+        };
+        let result = recycle_simulation(&kmir(2), &settings).unwrap();
+        assert!(result.converter.feed().as_array()[2] < 1e-12);
+        assert_eq!(result.purge[2], 0.);
+        assert!(result.ammonia_product > 0.);
+    }
+}
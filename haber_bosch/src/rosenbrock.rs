@@ -0,0 +1,279 @@
+//! A linearly implicit Rosenbrock method of order 2(1) for stiff systems.
+//!
+//! The `ode_solvers` crate only contains explicit methods. Close to the chemical equilibrium the
+//! Haber-Bosch kinetics become stiff, therefore we provide the ROS2 method of Verwer et al. with
+//! the same interface as the solvers of `ode_solvers`. The Jacobian is approximated by forward
+//! differences and the system is assumed to be autonomous, which holds for our reactor models.
+
+use nalgebra::SMatrix;
+use ode_solvers::dop_shared::{IntegrationError, Stats};
+use ode_solvers::System;
+
+use crate::simulation::{State, STATE_DIM};
+
+type Jacobian = SMatrix<f64, STATE_DIM, STATE_DIM>;
+
+/// gamma of the L-stable ROS2 method
+const GAMMA: f64 = 1. + std::f64::consts::FRAC_1_SQRT_2;
+
+pub struct Rosenbrock<F>
+where
+    F: System<State>,
+{
+    f: F,
+    x: f64,
+    x_end: f64,
+    dx: f64,
+    y: State,
+    rtol: f64,
+    atol: f64,
+    n_max: u32,
+    x_out: Vec<f64>,
+    y_out: Vec<State>,
+    stats: Stats,
+}
+
+impl<F> Rosenbrock<F>
+where
+    F: System<State>,
+{
+    /// Initializer for the structure, the arguments follow `ode_solvers::Dopri5::new`.
+    ///
+    /// * `f`       - Structure implementing the System<V> trait
+    /// * `x`       - Initial value of the independent variable
+    /// * `x_end`   - Final value of the independent variable
+    /// * `dx`      - Increment in the dense output
+    /// * `y`       - Initial value of the dependent variable(s)
+    /// * `rtol`    - Relative tolerance used in the computation of the adaptive step size
+    /// * `atol`    - Absolute tolerance used in the computation of the adaptive step size
+    /// * `n_max`   - Maximum number of integration steps
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        f: F,
+        x: f64,
+        x_end: f64,
+        dx: f64,
+        y: State,
+        rtol: f64,
+        atol: f64,
+        n_max: u32,
+    ) -> Self {
+        Rosenbrock {
+            f,
+            x,
+            x_end,
+            dx,
+            y,
+            rtol,
+            atol,
+            n_max,
+            x_out: Vec::new(),
+            y_out: Vec::new(),
+            stats: Stats {
+                num_eval: 0,
+                accepted_steps: 0,
+                rejected_steps: 0,
+            },
+        }
+    }
+
+    /// Core integration method.
+    pub fn integrate(&mut self) -> Result<Stats, IntegrationError> {
+        let span = self.x_end - self.x;
+        let mut h = self.dx.min(span);
+        let mut n_step = 0;
+        let mut xd = self.x;
+
+        self.x_out.push(self.x);
+        self.y_out.push(self.y);
+        xd += self.dx;
+
+        while self.x < self.x_end {
+            if n_step > self.n_max {
+                return Err(IntegrationError::MaxNumStepReached {
+                    x: self.x,
+                    n_step,
+                });
+            }
+            if 0.1 * h.abs() <= f64::EPSILON * self.x.abs() {
+                return Err(IntegrationError::StepSizeUnderflow { x: self.x });
+            }
+            if self.x + h > self.x_end {
+                h = self.x_end - self.x;
+            }
+            n_step += 1;
+
+            let Some((y_next, err)) = self.step(h) else {
+                // the iteration matrix is singular, retry with a smaller step
+                self.stats.rejected_steps += 1;
+                h *= 0.25;
+                continue;
+            };
+
+            if !err.is_finite() {
+                self.stats.rejected_steps += 1;
+                h *= 0.25;
+                continue;
+            }
+            let fac = (0.9 * err.max(1e-10).powf(-0.5)).clamp(0.2, 5.0);
+            if err > 1. {
+                self.stats.rejected_steps += 1;
+                h *= fac;
+                continue;
+            }
+            self.stats.accepted_steps += 1;
+
+            // dense output by linear interpolation between the last two steps
+            let x_old = self.x;
+            let y_old = self.y;
+            self.x += h;
+            self.y = y_next;
+            while xd <= self.x && xd <= self.x_end {
+                let theta = (xd - x_old) / h;
+                self.x_out.push(xd);
+                self.y_out.push(y_old + (self.y - y_old) * theta);
+                xd += self.dx;
+            }
+
+            let mut dy = State::zeros();
+            self.f.system(self.x, &self.y, &mut dy);
+            self.stats.num_eval += 1;
+            if self.f.solout(self.x, &self.y, &dy) {
+                if *self.x_out.last().unwrap() < self.x {
+                    self.x_out.push(self.x);
+                    self.y_out.push(self.y);
+                }
+                break;
+            }
+
+            h = (h * fac).min(span);
+        }
+
+        Ok(self.stats)
+    }
+
+    /// Performs one step of size `h` and returns the new state and the scaled error estimate.
+    fn step(&mut self, h: f64) -> Option<(State, f64)> {
+        let mut f0 = State::zeros();
+        self.f.system(self.x, &self.y, &mut f0);
+        let jac = self.jacobian(&f0);
+
+        let w = Jacobian::identity() - jac * (GAMMA * h);
+        let lu = w.lu();
+
+        let k1 = lu.solve(&f0)?;
+        let mut f1 = State::zeros();
+        self.f.system(self.x + h, &(self.y + k1 * h), &mut f1);
+        let k2 = lu.solve(&(f1 - k1 * 2.))?;
+        self.stats.num_eval += 2;
+
+        let y_next = self.y + k1 * (1.5 * h) + k2 * (0.5 * h);
+
+        // the difference to the embedded linearly implicit Euler step
+        let err_vec = (k1 + k2) * (0.5 * h);
+        let err = (err_vec
+            .iter()
+            .zip(self.y.iter().zip(y_next.iter()))
+            .map(|(e, (a, b))| {
+                let scale = self.atol + self.rtol * a.abs().max(b.abs());
+                (e / scale).powi(2)
+            })
+            .sum::<f64>()
+            / STATE_DIM as f64)
+            .sqrt();
+
+        Some((y_next, err))
+    }
+
+    /// Approximates the Jacobian of the system at the current state by forward differences.
+    fn jacobian(&mut self, f0: &State) -> Jacobian {
+        let mut jac = Jacobian::zeros();
+        let mut f1 = State::zeros();
+        for j in 0..STATE_DIM {
+            let delta = f64::EPSILON.sqrt() * self.y[j].abs().max(1e-5);
+            let mut y1 = self.y;
+            y1[j] += delta;
+            self.f.system(self.x, &y1, &mut f1);
+            jac.set_column(j, &((f1 - f0) / delta));
+        }
+        self.stats.num_eval += STATE_DIM as u32;
+        jac
+    }
+
+    /// Getter for the independent variable's output.
+    pub fn x_out(&self) -> &Vec<f64> {
+        &self.x_out
+    }
+
+    /// Getter for the dependent variables' output.
+    pub fn y_out(&self) -> &Vec<State> {
+        &self.y_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Linear decay with one stiff component, the solution is y_i(x) = exp(-LAMBDA_i x).
+    struct Decay;
+
+    const LAMBDA: [f64; 3] = [1., 5., 1000.];
+
+    impl System<State> for Decay {
+        fn system(&self, _x: f64, y: &State, dy: &mut State) {
+            *dy = State::zeros();
+            for (idx, lambda) in LAMBDA.iter().enumerate() {
+                dy[idx] = -lambda * y[idx];
+            }
+        }
+    }
+
+    fn initial() -> State {
+        let mut y = State::zeros();
+        y.fixed_rows_mut::<3>(0).fill(1.);
+        y
+    }
+
+    /// The largest error of the smooth components at x = 1 after `n` steps of fixed size.
+    fn fixed_step_error(n: u32) -> f64 {
+        let mut solver = Rosenbrock::new(Decay, 0., 1., 1., initial(), 1., 1., n);
+        let h = 1. / n as f64;
+        for _ in 0..n {
+            let (y_next, _) = solver.step(h).unwrap();
+            solver.x += h;
+            solver.y = y_next;
+        }
+        (0..2)
+            .map(|idx| (solver.y[idx] - (-LAMBDA[idx]).exp()).abs())
+            .fold(0., f64::max)
+    }
+
+    #[test]
+    fn converges_with_second_order() {
+        let (coarse, fine) = (fixed_step_error(200), fixed_step_error(400));
+        let order = (coarse / fine).log2();
+        assert!((order - 2.).abs() < 0.1, "observed order {}", order);
+    }
+
+    #[test]
+    fn damps_the_stiff_component() {
+        // far outside of the stability region of an explicit method
+        let mut solver = Rosenbrock::new(Decay, 0., 1., 1., initial(), 1., 1., 10);
+        let (y_next, _) = solver.step(0.1).unwrap();
+        assert!(y_next[2].abs() < 0.1);
+    }
+
+    #[test]
+    fn adaptive_integration_meets_the_tolerance() {
+        let mut solver = Rosenbrock::new(Decay, 0., 1., 0.1, initial(), 1e-6, 1e-9, 10000);
+        let stats = solver.integrate().unwrap();
+        assert!(stats.accepted_steps > 0);
+        assert!((solver.x_out().last().unwrap() - 1.).abs() < 1e-12);
+
+        let y_end = solver.y_out().last().unwrap();
+        for idx in 0..3 {
+            assert!((y_end[idx] - (-LAMBDA[idx]).exp()).abs() < 1e-4);
+        }
+    }
+}
@@ -1,15 +1,69 @@
-use std::f64::consts::E;
+use std::{cell::Cell, f64::consts::E, fmt::Display};
 
-use ode_solvers::{Vector6, System, Dopri5};
+use ode_solvers::dop_shared::{IntegrationError, OutputType, Stats};
+use ode_solvers::{Dop853, Dopri5, Rk4, SVector, System};
+use serde::Serialize;
 
 use crate::v2_hints::*;
-use crate::configuration::{Catalyst, HaberBoschBedSetup, HaberBoschInstance};
+use crate::configuration::{
+    BedSolverStats, CatalystKinetics, ErgunParameters, HaberBoschBedResult, HaberBoschBedSetup,
+    HaberBoschInstance, InterBedResult, InterBedUnit, SolverKind, SolverSettings, TemperatureMode,
+    TerminationReason,
+};
+use crate::rosenbrock::Rosenbrock;
 
-pub type State = Vector6<f64>; // 5 mixture components and temperature [nitrogn, hydrogen, ammonia, argon, metan, temperature]
+pub const STATE_DIM: usize = 7;
+pub type State = SVector<f64, STATE_DIM>; // 5 mixture components, temperature and pressure [nitrogn, hydrogen, ammonia, argon, metan, temperature, pressure]
 
-pub fn sequential_simulation(inst: &mut HaberBoschInstance, verbose: bool) {
-    for idx in 0..inst.len() {
-        let solver_info: HaberBoschSolverInfo = inst.get_solver_info(idx);
+/// Errors that can occur while simulating the reactor beds of a [`HaberBoschInstance`].
+///
+/// The bed indices are zero-based, the `Display` implementation prints them one-based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    /// The ODE solver failed while integrating the given bed.
+    Solver { bed: usize, message: String },
+    /// The state of the given bed contains NaN or infinite values at length `x`.
+    NonFiniteState { bed: usize, x: f64 },
+    /// The ODE solver did not produce any output for the given bed.
+    EmptyOutput { bed: usize },
+    /// The number of results does not match the number of reactor beds.
+    BedCountMismatch { beds: usize, results: usize },
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::Solver { bed, message } => {
+                write!(f, "Solver failed in bed {}: {}", bed + 1, message)
+            }
+            SimulationError::NonFiniteState { bed, x } => {
+                write!(f, "NaN or infinite state detected in bed {} at length {}", bed + 1, x)
+            }
+            SimulationError::EmptyOutput { bed } => {
+                write!(f, "Solver produced no output for bed {}", bed + 1)
+            }
+            SimulationError::BedCountMismatch { beds, results } => write!(
+                f,
+                "Bed count mismatch: {} reactor beds but {} results",
+                beds, results
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// Simulates the reactor beds of the given instance one after another, earlier results are
+/// replaced.
+///
+/// The simulation works on a copy, so `inst` is left untouched if any bed fails.
+pub fn sequential_simulation(
+    inst: &mut HaberBoschInstance,
+    verbose: bool,
+) -> Result<(), SimulationError> {
+    let mut wip = inst.with_feed(inst.feed(), inst.feed_flow());
+    for idx in 0..wip.len() {
+        let solver_info: HaberBoschSolverInfo = wip.get_solver_info(idx);
 
         if verbose {
             println!(
@@ -21,32 +75,236 @@ pub fn sequential_simulation(inst: &mut HaberBoschInstance, verbose: bool) {
             );
         }
 
-        // we need Dopri5 because it supports solout()
-        let mut stepper = Dopri5::new(
-            solver_info.model,
-            solver_info.x0,
-            solver_info.x0 + 25.,
-            25.0 / 2000.,
-            solver_info.y0,
-            10e-12,
-            10e-16,
-        );
-
-        //let mut stepper = Rk4::new(model, 0.0, y0, 25., 25. / 2000.);
-        let res = stepper.integrate();
-        match res {
-            Ok(stats) => {
-                if verbose {
-                    println! {"{}", stats};
-                }
+        let (res, wall_time) = measure_wall_time(|| integrate_bed(solver_info));
+        let ((stats, x_out, y_out), termination) = res.map_err(|e| SimulationError::Solver {
+            bed: idx,
+            message: e.to_string(),
+        })?;
+        if verbose {
+            println!("{}", stats);
+        }
+
+        check_bed_output(idx, &x_out, &y_out)?;
+
+        let stats = BedSolverStats {
+            num_eval: stats.num_eval,
+            accepted_steps: stats.accepted_steps,
+            rejected_steps: stats.rejected_steps,
+            wall_time,
+            termination,
+        };
+        wip.add_next_results(HaberBoschBedResult {
+            x_out,
+            y_out,
+            stats,
+            inlet: solver_info.inlet,
+        })?;
+    }
+
+    if wip.num_results() != wip.len() {
+        return Err(SimulationError::BedCountMismatch {
+            beds: wip.len(),
+            results: wip.num_results(),
+        });
+    }
+
+    *inst = wip;
+    Ok(())
+}
+
+/// Keeps the reason of the first stop of solout(), the solvers take the system by value.
+#[derive(Clone, Copy)]
+struct StopRecorder<'a> {
+    model: HaberBoschModel,
+    reason: &'a Cell<Option<TerminationReason>>,
+}
+
+impl System<State> for StopRecorder<'_> {
+    fn system(&self, x: f64, y: &State, dy: &mut State) {
+        self.model.system(x, y, dy);
+    }
+
+    fn solout(&mut self, _x: f64, y: &State, dy: &State) -> bool {
+        let reason = self.model.stop_reason(y, dy);
+        if reason.is_some() && self.reason.get().is_none() {
+            self.reason.set(reason);
+        }
+        reason.is_some()
+    }
+}
+
+/// Integrates a single reactor bed with the solver chosen in the settings of `info`.
+///
+/// Returns the output and why the integration ended.
+fn integrate_bed(
+    info: HaberBoschSolverInfo,
+) -> Result<(SolverOutput, TerminationReason), IntegrationError> {
+    let reason = Cell::new(None);
+    let system = StopRecorder {
+        model: info.model,
+        reason: &reason,
+    };
+    let out = integrate_with(info, system)?;
+    Ok((out, reason.get().unwrap_or(TerminationReason::EndOfSpan)))
+}
+
+fn integrate_with(
+    info: HaberBoschSolverInfo,
+    system: StopRecorder,
+) -> Result<SolverOutput, IntegrationError> {
+    let settings = info.settings;
+    let x_end = info.x0 + settings.max_bed_length;
+
+    match settings.kind {
+        SolverKind::Dopri5 => {
+            let mut stepper = Dopri5::from_param(
+                system,
+                info.x0,
+                x_end,
+                settings.output_step,
+                info.y0,
+                settings.rtol,
+                settings.atol,
+                0.9,
+                0.04,
+                0.2,
+                10.0,
+                settings.max_bed_length,
+                0.,
+                settings.max_steps,
+                1000,
+                OutputType::Dense,
+            );
+            let stats = stepper.integrate()?;
+            Ok((stats, stepper.x_out().clone(), stepper.y_out().clone()))
+        }
+        SolverKind::Dop853 => integrate_in_chunks(&info, system, |x0, y0, x_end| {
+            let mut stepper = Dop853::from_param(
+                system,
+                x0,
+                x_end,
+                settings.output_step,
+                y0,
+                settings.rtol,
+                settings.atol,
+                0.9,
+                0.,
+                0.333,
+                6.0,
+                settings.max_bed_length,
+                0.,
+                settings.max_steps,
+                1000,
+                OutputType::Dense,
+            );
+            let stats = stepper.integrate()?;
+            Ok((stats, stepper.x_out().clone(), stepper.y_out().clone()))
+        }),
+        SolverKind::Rk4 => integrate_in_chunks(&info, system, |x0, y0, x_end| {
+            let mut stepper = Rk4::new(system, x0, y0, x_end, settings.output_step);
+            let stats = stepper.integrate()?;
+            Ok((stats, stepper.x_out().clone(), stepper.y_out().clone()))
+        }),
+        SolverKind::Rosenbrock => {
+            // the builder rejects tolerances the method cannot meet
+            let mut stepper = Rosenbrock::new(
+                system,
+                info.x0,
+                x_end,
+                settings.output_step,
+                info.y0,
+                settings.rtol,
+                settings.atol,
+                settings.max_steps,
+            );
+            let stats = stepper.integrate()?;
+            Ok((stats, stepper.x_out().clone(), stepper.y_out().clone()))
+        }
+    }
+}
+
+type SolverOutput = (Stats, Vec<f64>, Vec<State>);
+
+/// Dop853 and Rk4 never call solout(), so they integrate `SOLOUT_CHUNK_STEPS` output steps at a
+/// time and stop after the first chunk in which solout() would have stopped the solver.
+///
+/// `integrate` gets the start, the initial state and the end of a chunk. The statistics add up
+/// the chunks, so they describe the work that was actually done.
+fn integrate_in_chunks(
+    info: &HaberBoschSolverInfo,
+    mut system: StopRecorder,
+    mut integrate: impl FnMut(f64, State, f64) -> Result<SolverOutput, IntegrationError>,
+) -> Result<SolverOutput, IntegrationError> {
+    let settings = info.settings;
+    let x_end = info.x0 + settings.max_bed_length;
+    let chunk = settings.output_step * SOLOUT_CHUNK_STEPS as f64;
+
+    let mut dy = State::zeros();
+    let mut stats = Stats {
+        num_eval: 0,
+        accepted_steps: 0,
+        rejected_steps: 0,
+    };
+    let (mut x_out, mut y_out) = (vec![info.x0], vec![info.y0]);
+    loop {
+        let (x0, y0) = (*x_out.last().unwrap(), *y_out.last().unwrap());
+        let (chunk_stats, xs, ys) = integrate(x0, y0, (x0 + chunk).min(x_end))?;
+        stats.num_eval += chunk_stats.num_eval;
+        stats.accepted_steps += chunk_stats.accepted_steps;
+        stats.rejected_steps += chunk_stats.rejected_steps;
+
+        // the first point of a chunk repeats the last point of the previous one
+        for (x, y) in xs.iter().zip(ys.iter()).skip(1) {
+            x_out.push(*x);
+            y_out.push(*y);
+            system.system(*x, y, &mut dy);
+            if system.solout(*x, y, &dy) {
+                return Ok((stats, x_out, y_out));
             }
-            Err(e) => println!("Error: {}", e),
         }
+        if xs.last().is_none_or(|x| *x <= x0 || *x >= x_end) {
+            return Ok((stats, x_out, y_out));
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance)]
+    fn now() -> f64;
+}
+
+/// Runs `f` and returns its result and the elapsed wall-clock time in seconds.
+///
+/// `std::time::Instant` panics in the browser, there we use `performance.now()` instead.
+fn measure_wall_time<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let start = now();
+        let res = f();
+        (res, (now() - start) / 1000.)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let start = std::time::Instant::now();
+        let res = f();
+        (res, start.elapsed().as_secs_f64())
+    }
+}
 
-        let x_out = stepper.x_out();
-        let y_out = stepper.y_out();
+fn check_bed_output(bed: usize, x_out: &[f64], y_out: &[State]) -> Result<(), SimulationError> {
+    if x_out.is_empty() || y_out.is_empty() {
+        return Err(SimulationError::EmptyOutput { bed });
+    }
 
-        inst.add_next_results(x_out.clone(), y_out.clone());
+    let non_finite = x_out
+        .iter()
+        .zip(y_out.iter())
+        .find(|(x, y)| !x.is_finite() || y.iter().any(|v| !v.is_finite()));
+    match non_finite {
+        Some((x, _)) => Err(SimulationError::NonFiniteState { bed, x: *x }),
+        None => Ok(()),
     }
 }
 
@@ -59,6 +317,9 @@ pub struct HaberBoschModel {
     pub beta: f64,
     pub t_slope: f64,
     pub t_max: f64,
+    pub temperature_mode: TemperatureMode,
+    pub pressure_drop: Option<ErgunParameters>,
+    pub stop_threshold: f64,
 }
 
 /// This type is the answer to:
@@ -68,56 +329,260 @@ pub struct HaberBoschSolverInfo {
     pub model: HaberBoschModel,
     pub x0: f64,
     pub y0: State,
+    pub settings: SolverSettings,
+    pub inlet: Option<InterBedResult>,
 }
 
 
 pub const R: f64 = 1.987; // cal/(mol·K) (GAS CONSTANT changed unit compared to video 1)
 
+/// Applies the unit in front of `bed` to the gas `y_prev` leaving the previous bed.
+///
+/// `flow_per_pressure` [mol/s per bar] converts the state of the previous bed into molar flows,
+/// see `HaberBoschInstance::flow_per_pressure`. Returns the inlet state of the bed and the duty
+/// or quench flow of the unit. A quench can only cool the gas, if the gas is already colder than
+/// `t_start` no quench gas is added.
+pub fn inter_bed_unit(
+    inst: &HaberBoschInstance,
+    bed: HaberBoschBedSetup,
+    y_prev: &State,
+    flow_per_pressure: f64,
+) -> (State, InterBedResult) {
+    let n_prev = y_prev.iter().take(5).sum::<f64>();
+    let t_in = y_prev[5];
+    let mut y0 = *y_prev;
+
+    match bed.inter_bed {
+        InterBedUnit::HeatExchanger => {
+            y0[5] = bed.t_start;
+            // cal/s to kW
+            let duty = n_prev * flow_per_pressure * mixture_heat_capacity(y_prev)
+                * (t_in - bed.t_start)
+                * 4.184
+                / 1000.;
+            let inlet = InterBedResult {
+                t_in,
+                t_out: bed.t_start,
+                duty,
+                ..Default::default()
+            };
+            (y0, inlet)
+        }
+        InterBedUnit::Quench { feed_temperature } => {
+            if t_in <= bed.t_start || feed_temperature >= bed.t_start {
+                let inlet = InterBedResult {
+                    t_in,
+                    t_out: t_in,
+                    ..Default::default()
+                };
+                return (y0, inlet);
+            }
+
+            // energy balance of the mixing point decides about the amount of quench gas
+            let feed = inst.feed_state();
+            let n_feed = feed.iter().take(5).sum::<f64>();
+            let n_quench = n_prev * mixture_heat_capacity(y_prev) * (t_in - bed.t_start)
+                / (mixture_heat_capacity(&feed) * (bed.t_start - feed_temperature));
+            for idx in 0..5 {
+                y0[idx] += feed[idx] / n_feed * n_quench;
+            }
+            // the mixed gas is at the pressure of the bed again, the states are scaled to the
+            // inlet pressure of the first bed (see HaberBoschModel::system)
+            let n_mixed = n_prev + n_quench;
+            for idx in 0..5 {
+                y0[idx] *= inst.pres() / n_mixed;
+            }
+            y0[5] = bed.t_start;
+
+            let quench_flow = n_quench * flow_per_pressure;
+            let inlet = InterBedResult {
+                t_in,
+                t_out: bed.t_start,
+                quench_ratio: quench_flow / inst.feed_flow(),
+                quench_flow,
+                ..Default::default()
+            };
+            (y0, inlet)
+        }
+    }
+}
+
+/// Pressure gradient along the bed in bar per length unit [m] given by the Ergun equation.
+pub fn ergun_pressure_gradient(ergun: &ErgunParameters, y: &State) -> f64 {
+    let n_total = y.iter().take(5).sum::<f64>();
+    let molar_mass = y
+        .iter()
+        .take(5)
+        .zip(MOLAR_MASSES.iter())
+        .map(|(n, m)| n * m)
+        .sum::<f64>()
+        / n_total;
+    // ideal gas density in kg/m³, the pressure is converted from bar to Pa
+    let density = y[6] * 1e5 * molar_mass / (R_SI * y[5]);
+
+    let (d, eps, u) = (
+        ergun.particle_diameter,
+        ergun.void_fraction,
+        ergun.superficial_velocity,
+    );
+    let viscous = 150. * GAS_VISCOSITY * (1. - eps).powi(2) * u / (eps.powi(3) * d.powi(2));
+    let inertial = 1.75 * density * (1. - eps) * u.powi(2) / (eps.powi(3) * d);
+
+    // Pa/m to bar/m
+    -(viscous + inertial) / 1e5
+}
+
+/// Molar heat capacity of the gas mixture in cal/(mol·K), weighted by the partial pressures.
+pub fn mixture_heat_capacity(y: &State) -> f64 {
+    let n_total = y.iter().take(5).sum::<f64>();
+    y.iter()
+        .take(5)
+        .zip(HEAT_CAPACITIES.iter())
+        .map(|(n, cp)| n * cp)
+        .sum::<f64>()
+        / n_total
+}
+
 impl HaberBoschModel {
-    pub fn new(pressure: f64, catalyst: Catalyst, bed: HaberBoschBedSetup) -> Self {
-        match catalyst {
-            Catalyst::FN => HaberBoschModel {
-                pressure,
-                ea: FN_EA,
-                big_a: FN_BIG_A,
-                beta: bed.beta,
-                t_slope: bed.t_slope,
-                t_max: bed.t_max,
-                alpha: FN_ALPHA,
-            },
-            Catalyst::KMIR => HaberBoschModel {
-                pressure,
-                ea: KMIR_EA,
-                big_a: KMIR_BIG_A,
-                beta: bed.beta,
-                t_slope: bed.t_slope,
-                t_max: bed.t_max,
-                alpha: KMIR_ALPHA,
-            },
+    pub fn new(pressure: f64, catalyst: &CatalystKinetics, bed: HaberBoschBedSetup) -> Self {
+        HaberBoschModel {
+            pressure,
+            ea: catalyst.ea,
+            big_a: catalyst.big_a,
+            beta: bed.beta,
+            t_slope: bed.t_slope,
+            t_max: bed.t_max,
+            alpha: catalyst.alpha,
+            temperature_mode: bed.temperature_mode,
+            pressure_drop: bed.pressure_drop,
+            stop_threshold: AMMONIA_THRESHOLD,
         }
     }
 }
 
-impl System<State> for HaberBoschModel {
-    fn system(&self, _x: f64, y: &State, dy: &mut State) {
-        // use named variables
-        let (n2, h2, nh3) = (y[0], y[1], y[2]);
-        // y[3] and y[4] are inerts they don't change.
-        let temp = y[5];
+/// Equilibrium constant of the ammonia synthesis at `temp` in K, `beta` is the bed parameter.
+pub fn equilibrium_constant(temp: f64, beta: f64) -> f64 {
+    let log10_ka = beta * temp.log10() - 5.519265e-5 * temp
+        + 1.848863e-7 * temp.powi(2)
+        + (2001.6 / temp)
+        + 2.6899;
+    10f64.powf(log10_ka)
+}
+
+/// Fugacity coefficients of N2, H2 and NH3 at `t` in K and `p` in bar.
+pub fn fugacity_coefficients(t: f64, p: f64) -> [f64; 3] {
+    // helper variables:
+    let t_squared = t.powi(2);
+    let p_squared = p.powi(2);
+
+    // fugacity calculations as described in model:
+    let n2 = 0.93431737 + 0.3101804 * 10f64.powi(-3) * t + 0.295896 * 10f64.powi(-3) * p
+        - 0.2707279 * 10f64.powi(-6) * t_squared
+        + 0.4775207 * 10f64.powi(-6) * p_squared;
+
+    let h2 = E.powf(
+        E.powf(-3.84027 * t.powf(1.25) + 0.541) * p
+            - E.powf(-0.012637 * t.powf(0.5) - 15.980) * p.powf(2.0)
+            + 300.0 * E.powf(-0.0119017 * t - 5.941) * (E.powf(-p / 300.0) - 1.0),
+    );
+
+    let nh3 = 0.1438996 + 0.2028538 * 10f64.powi(-2) * t
+        - 0.448762 * 10f64.powi(-3) * p
+        - 0.1142945 * 10f64.powi(-5) * t_squared
+        + 0.2761216 * 10f64.powi(-6) * p_squared;
+
+    [n2, h2, nh3]
+}
+
+/// The intermediate quantities of [`HaberBoschModel::system`] for one state.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct RateTerms {
+    /// rate constant of the catalyst
+    pub k: f64,
+    /// equilibrium constant
+    pub ka: f64,
+    /// forward term of the rate expression
+    pub t1: f64,
+    /// reverse term of the rate expression
+    pub t2: f64,
+    /// formation rate of ammonia before the fugacity correction, k * (t1 - t2)
+    pub rnh3: f64,
+    /// [-] fugacity coefficients of N2, H2 and NH3
+    pub fugacity: [f64; 3],
+}
+
+impl RateTerms {
+    /// [-] reaction quotient over its equilibrium value, t2 / t1. It approaches one when the
+    /// bed stalls at equilibrium.
+    pub fn approach(&self) -> f64 {
+        self.t2 / self.t1
+    }
+}
+
+impl HaberBoschModel {
+    /// Formation rate of ammonia for the partial pressures in bar, without the fugacity
+    /// correction. It is zero at equilibrium and negative above it.
+    pub fn ammonia_rate(&self, temp: f64, n2: f64, h2: f64, nh3: f64) -> f64 {
+        self.rate_terms_at(temp, n2, h2, nh3).rnh3
+    }
+
+    fn rate_terms_at(&self, temp: f64, n2: f64, h2: f64, nh3: f64) -> RateTerms {
+        // both pressures end up in a denominator, without ammonia the forward term would be
+        // infinite and without hydrogen the reverse term
+        let (h2, nh3) = (h2.max(MIN_PARTIAL_PRESSURE), nh3.max(MIN_PARTIAL_PRESSURE));
 
         // intermediate calculations for reaction rates
         let k = self.big_a * (-self.ea / (R * temp)).exp();
-        let log10_ka = self.beta * temp.log10() - 5.519265e-5 * temp
-            + 1.848863e-7 * temp.powi(2)
-            + (2001.6 / temp)
-            + 2.6899;
-        let ka = 10f64.powf(log10_ka);
+        let ka = equilibrium_constant(temp, self.beta);
 
         let t1 = ka.powi(2) * n2 * (h2.powi(3) / nh3.powi(2)).powf(self.alpha);
         let t2 = (nh3.powi(2) / h2.powi(3)).powf(1. - self.alpha);
+        RateTerms {
+            k,
+            ka,
+            t1,
+            t2,
+            rnh3: k * (t1 - t2),
+            fugacity: [1.; 3],
+        }
+    }
 
+    /// Why the bed ends at the state `y` with the derivatives `dy`, None while it still forms
+    /// enough ammonia.
+    pub fn stop_reason(&self, y: &State, dy: &State) -> Option<TerminationReason> {
+        // stop solver if changes of ammonia are close to zero.
+        if dy[2] >= self.stop_threshold {
+            return None;
+        }
+        // the ramp oscillates around t_max once it got there
+        Some(if y[5] >= self.t_max - T_MAX_TOLERANCE {
+            TerminationReason::TemperatureLimit
+        } else {
+            TerminationReason::AmmoniaThreshold
+        })
+    }
+
+    /// The rate terms for the state `y` of the solver, this is what `system` works with.
+    pub fn rate_terms(&self, y: &State) -> RateTerms {
+        // use named variables, the partial pressures are scaled to the local pressure
+        let p_local = y[6];
+        let scale = p_local / self.pressure;
+        let (n2, h2, nh3) = (y[0] * scale, y[1] * scale, y[2] * scale);
+        // y[3] and y[4] are inerts they don't change.
+        let temp = y[5];
+
+        RateTerms {
+            fugacity: fugacity_coefficients(temp, p_local),
+            ..self.rate_terms_at(temp, n2, h2, nh3)
+        }
+    }
+}
+
+impl System<State> for HaberBoschModel {
+    fn system(&self, _x: f64, y: &State, dy: &mut State) {
         // reaction rates based on provided model
-        let rnh3 = k * (t1 - t2);
+        let terms = self.rate_terms(y);
+        let rnh3 = terms.rnh3;
         let rn2 = rnh3 / 2.;
         let rh2 = rnh3 / 2. * 3.;
 
@@ -129,7 +594,7 @@ impl System<State> for HaberBoschModel {
         dy[3] = 0.;
         dy[4] = 0.;
 
-        // temperature
+        // temperature (ramp, the adiabatic mode is handled after the fugacity correction)
         dy[5] = self.t_slope;
         dy[5] = if y[5] < self.t_max {
             dy[5]
@@ -137,37 +602,351 @@ impl System<State> for HaberBoschModel {
             self.t_max - y[5]
         };
 
-        // calculate fugacity components
+        // fugacity components
         let mut fug = State::default();
         fug = fug.add_scalar(1.); // vector containing ones
+        fug[0] = terms.fugacity[0];
+        fug[1] = terms.fugacity[1];
+        fug[2] = terms.fugacity[2];
 
-        // helper variables:
-        let (t, p) = (temp, self.pressure);
-        let t_squared = t.powi(2);
-        let p_squared = p.powi(2);
+        // component-wise multiplication
+        *dy = dy.component_mul(&fug);
 
-        // fugacity calculations as described in model:
-        fug[0] = 0.93431737 + 0.3101804 * 10f64.powi(-3) * t + 0.295896 * 10f64.powi(-3) * p
-            - 0.2707279 * 10f64.powi(-6) * t_squared
-            + 0.4775207 * 10f64.powi(-6) * p_squared;
+        if self.temperature_mode == TemperatureMode::Adiabatic {
+            // the heat released by the formed ammonia warms up the gas mixture
+            let n_total = y.iter().take(5).sum::<f64>();
+            dy[5] = -HEAT_OF_REACTION * dy[2] / (n_total * mixture_heat_capacity(y));
+        }
 
-        fug[1] = E.powf(
-            E.powf(-3.84027 * t.powf(1.25) + 0.541) * p
-                - E.powf(-0.012637 * t.powf(0.5) - 15.980) * p.powf(2.0)
-                + 300.0 * E.powf(-0.0119017 * t - 5.941) * (E.powf(-p / 300.0) - 1.0),
-        );
+        // pressure
+        dy[6] = match &self.pressure_drop {
+            Some(ergun) => ergun_pressure_gradient(ergun, y),
+            None => 0.,
+        };
+    }
 
-        fug[2] = 0.1438996 + 0.2028538 * 10f64.powi(-2) * t
-            - 0.448762 * 10f64.powi(-3) * p
-            - 0.1142945 * 10f64.powi(-5) * t_squared
-            + 0.2761216 * 10f64.powi(-6) * p_squared;
+    fn solout(&mut self, _x: f64, y: &State, dy: &State) -> bool {
+        self.stop_reason(y, dy).is_some()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{Catalyst, FeedComposition, HaberBoschInstanceBuilder};
+    use crate::summary::nh3_fraction;
+    use crate::test_fixtures::kmir_builder;
 
-        // component-wise multiplication
-        *dy = dy.component_mul(&fug);
+    fn kmir_model() -> HaberBoschModel {
+        let bed = HaberBoschBedSetup {
+            beta: KMIR_B1_B,
+            ..Default::default()
+        };
+        HaberBoschModel::new(KMIR_REACTOR_PRESSURE, &Catalyst::KMIR.into(), bed)
     }
 
-    fn solout(&mut self, _x: f64, _y: &State, dy: &State) -> bool {
-        // stop solver if changes of ammonia are close to zero.
-        return dy[2] < AMMONIA_THRESHOLD;
+    #[test]
+    fn rate_is_finite_without_ammonia() {
+        let model = kmir_model();
+        let terms = model.rate_terms_at(KMIR_B1_TS, 50., 150., 0.);
+        assert!(terms.rnh3.is_finite() && terms.rnh3 > 0.);
+        assert!(terms.t2 < 1e-9);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rate_is_finite_without_hydrogen() {
+        let model = kmir_model();
+        let terms = model.rate_terms_at(KMIR_B1_TS, 50., 0., 20.);
+        assert!(terms.rnh3.is_finite() && terms.rnh3 < 0.);
+    }
+
+    fn kmir_with(kind: SolverKind) -> HaberBoschInstance {
+        let settings = SolverSettings {
+            kind,
+            ..Default::default()
+        };
+        let mut inst = kmir_builder(2).with_solver_settings(settings).build().unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+        inst
+    }
+
+    #[test]
+    fn chunked_solvers_stop_like_dopri5() {
+        let reference = kmir_with(SolverKind::Dopri5).summary().unwrap();
+        let step = SolverSettings::default().output_step;
+        for kind in [SolverKind::Dop853, SolverKind::Rk4] {
+            let inst = kmir_with(kind);
+            let summary = inst.summary().unwrap();
+            assert!((summary.final_yield - reference.final_yield).abs() < 1e-3);
+            for (bed, ref_bed) in summary.beds.iter().zip(&reference.beds) {
+                assert!((bed.length - ref_bed.length).abs() <= 2. * step);
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_solver_stats_end_with_the_output() {
+        // Rk4 takes one step per output point, the work may only exceed the output by a chunk
+        for res in kmir_with(SolverKind::Rk4).results() {
+            let steps = res.stats.accepted_steps as usize;
+            assert!(steps + 1 >= res.x_out.len());
+            assert!(steps < res.x_out.len() + SOLOUT_CHUNK_STEPS);
+            assert_eq!(res.stats.num_eval as usize, 4 * steps);
+        }
+    }
+
+    fn termination(kind: SolverKind, t_slope: f64, max_bed_length: f64) -> TerminationReason {
+        let settings = SolverSettings {
+            kind,
+            max_bed_length,
+            output_step: 0.005,
+            ..Default::default()
+        };
+        let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .with_solver_settings(settings)
+            .add_bed(KMIR_B1_TS, t_slope, KMIR_B1_TS + 20., KMIR_B1_B)
+            .build()
+            .unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+        inst.results()[0].stats.termination
+    }
+
+    #[test]
+    fn records_the_termination_reason_where_the_solver_stops() {
+        for kind in [SolverKind::Dopri5, SolverKind::Rk4] {
+            assert_eq!(
+                termination(kind, 0., 25.),
+                TerminationReason::AmmoniaThreshold
+            );
+            // the steep ramp holds the bed at t_max long before the reaction stalls
+            assert_eq!(
+                termination(kind, 2000., 25.),
+                TerminationReason::TemperatureLimit
+            );
+            assert_eq!(termination(kind, 0., 0.05), TerminationReason::EndOfSpan);
+        }
+    }
+
+    #[test]
+    fn quench_mixes_at_the_bed_pressure() {
+        let mut quenched = HaberBoschBedSetup {
+            t_start: KMIR_B2_TS,
+            temperature_mode: TemperatureMode::Adiabatic,
+            ..Default::default()
+        };
+        quenched.set_quench(423.);
+        let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .build()
+            .unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+
+        let y_prev = *inst.results()[0].y_out.last().unwrap();
+        let flow_prev = inst.flow_per_pressure(0) * y_prev.iter().take(5).sum::<f64>();
+        let (y0, inlet) = inter_bed_unit(&inst, quenched, &y_prev, inst.flow_per_pressure(0));
+
+        assert!(inlet.quench_flow > 0.);
+        assert_eq!(y0[5], KMIR_B2_TS);
+        assert_eq!(y0[6], y_prev[6]);
+        // the partial pressures of the mixed gas add up to the bed pressure again
+        let p_total = y0.iter().take(5).sum::<f64>() * y0[6] / inst.pres();
+        assert!((p_total - y0[6]).abs() < 1e-9);
+
+        // the mixed gas holds the ammonia of the previous bed and of the quench gas
+        let feed = inst.feed_state();
+        let flow_mixed = flow_prev + inlet.quench_flow;
+        let nh3_prev = inst.flow_per_pressure(0) * y_prev[2];
+        let nh3_quench = inlet.quench_flow * feed[2] / feed.iter().take(5).sum::<f64>();
+        let nh3_mixed = y0[2] / inst.pres() * flow_mixed;
+        assert!((nh3_mixed - nh3_prev - nh3_quench).abs() < 1e-9 * flow_mixed);
+        assert!((inlet.quench_ratio - inlet.quench_flow / inst.feed_flow()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn outlet_flows_include_the_quench_gas() {
+        let mut quenched = HaberBoschBedSetup::from_constants(1, "KMIR");
+        quenched.set_quench(423.);
+        let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .add_bed_setup(quenched)
+            .build()
+            .unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+
+        // argon is inert, it leaves with the feed and the quench gas
+        let feed = inst.feed_state();
+        let ar_fraction = feed[3] / feed.iter().take(5).sum::<f64>();
+        let quench_flow = inst.inter_bed_results()[0].quench_flow;
+        let ar_in = inst.feed_flow() * inst.feed().as_array()[3] + quench_flow * ar_fraction;
+        let ar_out = inst.outlet_flows().unwrap()[3];
+        assert!((ar_out - ar_in).abs() < 1e-9 * ar_in);
+    }
+
+    #[test]
+    fn simulates_feed_without_ammonia() {
+        let feed = FeedComposition::new(0.25, 0.75, 0., 0., 0.).unwrap();
+        let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .with_feed_composition(feed)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .build()
+            .unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+
+        let res = &inst.results()[0];
+        assert!(res.y_out.iter().all(|y| y.iter().all(|v| v.is_finite())));
+        assert!(res.y_out.last().unwrap()[2] > 0.);
+    }
+
+    /// Two KMIR beds, the pressure drop of the second one is far beyond any real packing.
+    fn with_crushing_pressure_drop(kind: SolverKind) -> HaberBoschInstance {
+        let crushed = HaberBoschBedSetup {
+            pressure_drop: Some(ErgunParameters::new(1e-4, 0.4, 1000.)),
+            ..HaberBoschBedSetup::from_constants(1, "KMIR")
+        };
+        let settings = SolverSettings {
+            kind,
+            ..Default::default()
+        };
+        HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .with_solver_settings(settings)
+            .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
+            .add_bed_setup(crushed)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn failing_bed_leaves_the_instance_untouched() {
+        // Rk4 integrates into the negative pressures, Dop853 gives up on the step size
+        let mut inst = with_crushing_pressure_drop(SolverKind::Rk4);
+        let err = sequential_simulation(&mut inst, false).unwrap_err();
+        assert!(matches!(err, SimulationError::NonFiniteState { bed: 1, .. }), "{:?}", err);
+        assert_eq!(inst.num_results(), 0);
+
+        let mut inst = with_crushing_pressure_drop(SolverKind::Dop853);
+        let err = sequential_simulation(&mut inst, false).unwrap_err();
+        assert!(matches!(err, SimulationError::Solver { bed: 1, .. }), "{:?}", err);
+        assert_eq!(inst.num_results(), 0);
+        assert_eq!(inst.len(), 2);
+    }
+
+    #[test]
+    fn simulating_again_replaces_the_results() {
+        let mut inst = kmir_with(SolverKind::Dopri5);
+        let first = inst.summary().unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+        assert_eq!(inst.num_results(), inst.len());
+        assert_eq!(inst.summary().unwrap().final_yield, first.final_yield);
+    }
+
+    fn adiabatic_model(t_slope: f64) -> HaberBoschModel {
+        let bed = HaberBoschBedSetup {
+            t_slope,
+            temperature_mode: TemperatureMode::Adiabatic,
+            ..HaberBoschBedSetup::from_constants(0, "KMIR")
+        };
+        HaberBoschModel::new(KMIR_REACTOR_PRESSURE, &Catalyst::KMIR.into(), bed)
+    }
+
+    #[test]
+    fn adiabatic_beds_heat_up_by_the_heat_of_reaction() {
+        let y = State::from([50., 140., 8., 1., 1., 720., KMIR_REACTOR_PRESSURE]);
+        let mut dy = State::zeros();
+        adiabatic_model(KMIR_B1_TR).system(0., &y, &mut dy);
+
+        let n_total = 200.;
+        let expected = -HEAT_OF_REACTION * dy[2] / (n_total * mixture_heat_capacity(&y));
+        assert!(dy[2] > 0.);
+        assert!((dy[5] - expected).abs() < 1e-12 * expected.abs());
+
+        // the ramp of the bed plays no role
+        let mut dy_flat = State::zeros();
+        adiabatic_model(0.).system(0., &y, &mut dy_flat);
+        assert_eq!(dy, dy_flat);
+    }
+
+    #[test]
+    fn adiabatic_bed_warms_up_with_the_formed_ammonia() {
+        let bed = HaberBoschBedSetup {
+            temperature_mode: TemperatureMode::Adiabatic,
+            ..HaberBoschBedSetup::from_constants(0, "KMIR")
+        };
+        let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+            .add_bed_setup(bed)
+            .build()
+            .unwrap();
+        sequential_simulation(&mut inst, false).unwrap();
+
+        let y_out = &inst.results()[0].y_out;
+        assert!(y_out.len() > 2);
+        for pair in y_out.windows(2) {
+            assert!(nh3_fraction(&pair[1]) > nh3_fraction(&pair[0]));
+            assert!(pair[1][5] > pair[0][5]);
+        }
+    }
+
+    fn with_pressure_drop(pressure_drop: Option<ErgunParameters>) -> HaberBoschModel {
+        let bed = HaberBoschBedSetup {
+            pressure_drop,
+            ..HaberBoschBedSetup::from_constants(0, "KMIR")
+        };
+        HaberBoschModel::new(KMIR_REACTOR_PRESSURE, &Catalyst::KMIR.into(), bed)
+    }
+
+    #[test]
+    fn pressure_falls_by_the_ergun_equation() {
+        // N2 and H2 only, M = (50 * 0.028 + 150 * 0.002) / 200 = 0.0085 kg/mol
+        let y = State::from([50., 150., 0., 0., 0., 700., KMIR_REACTOR_PRESSURE]);
+        let ergun = ErgunParameters::new(0.006, 0.4, 0.5);
+        let mut dy = State::zeros();
+        with_pressure_drop(Some(ergun)).system(0., &y, &mut dy);
+
+        let density = 200e5 * 0.0085 / (8.314 * 700.);
+        // 150 µ (1 - ε)² u / (ε³ d²) and 1.75 (1 - ε) u² / (ε³ d) in Pa/m
+        let expected = -(351.5625 + 683.59375 * density) / 1e5;
+        assert!((dy[6] - expected).abs() < 1e-12 * expected.abs());
+        assert_eq!(ergun_pressure_gradient(&ergun, &y), dy[6]);
+
+        with_pressure_drop(None).system(0., &y, &mut dy);
+        assert_eq!(dy[6], 0.);
+    }
+
+    #[test]
+    fn fugacities_follow_the_local_pressure() {
+        let model = with_pressure_drop(None);
+        let mut y = State::from([50., 140., 8., 1., 1., 720., 150.]);
+        let terms = model.rate_terms(&y);
+        assert_eq!(terms.fugacity, fugacity_coefficients(720., 150.));
+        assert_ne!(terms.fugacity, fugacity_coefficients(720., KMIR_REACTOR_PRESSURE));
+
+        let mut dy = State::zeros();
+        model.system(0., &y, &mut dy);
+        assert_eq!(dy[2], terms.rnh3 * terms.fugacity[2]);
+
+        // the same state at the inlet pressure forms ammonia faster
+        y[6] = KMIR_REACTOR_PRESSURE;
+        assert!(model.rate_terms(&y).rnh3 > terms.rnh3);
+    }
+
+    #[test]
+    fn pressure_falls_along_beds_with_a_pressure_drop() {
+        let simulate = |pressure_drop| {
+            let bed = HaberBoschBedSetup {
+                pressure_drop,
+                ..HaberBoschBedSetup::from_constants(0, "KMIR")
+            };
+            let mut inst = HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR)
+                .add_bed_setup(bed)
+                .build()
+                .unwrap();
+            sequential_simulation(&mut inst, false).unwrap();
+            inst.results()[0].y_out.iter().map(|y| y[6]).collect::<Vec<_>>()
+        };
+
+        let falling = simulate(Some(ErgunParameters::new(0.006, 0.4, 0.5)));
+        assert!(falling.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(*falling.last().unwrap() < KMIR_REACTOR_PRESSURE);
+
+        let constant = simulate(None);
+        assert!(constant.iter().all(|p| *p == KMIR_REACTOR_PRESSURE));
+    }
+}
@@ -20,6 +20,7 @@ pub struct HaberBoschModel {
 }
 
 // implementation of the System<V> trait to communicate about the system of equations with the ode solver.
+#[allow(clippy::neg_multiply)] // keep the stoichiometric coefficients visible
 impl System<State> for HaberBoschModel {
     fn system(&self, _x: f64, y: &State, dy: &mut State) {
         dy[0] = -1. * self.rf * y[0] * y[1] + 1. * self.rr * y[2].powi(2);
@@ -0,0 +1,208 @@
+//! Key figures of a simulated [`HaberBoschInstance`].
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::configuration::{BedSolverStats, HaberBoschInstance, InterBedResult};
+use crate::simulation::State;
+
+/// [-] ammonia mole fraction of a solver state
+pub(crate) fn nh3_fraction(y: &State) -> f64 {
+    y[2] / y.iter().take(5).sum::<f64>()
+}
+
+/// Key figures of a single reactor bed, temperatures are given in °C.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct BedSummary {
+    /// the reactor bed, starting at 1
+    pub bed: u32,
+    /// length of the bed
+    pub length: f64,
+    pub t_in: f64,
+    pub t_out: f64,
+    /// [-] ammonia mole fraction at the inlet
+    pub nh3_in: f64,
+    /// [-] ammonia mole fraction at the outlet
+    pub nh3_out: f64,
+    /// [-] fraction of the nitrogen entering the bed that reacts in it
+    pub conversion_n2: f64,
+    /// [-] fraction of the hydrogen entering the bed that reacts in it
+    pub conversion_h2: f64,
+    /// [bar]
+    pub pressure_out: f64,
+}
+
+/// The results of a simulation as values, replaces printing them directly.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationSummary {
+    pub catalyst: String,
+    pub beds: Vec<BedSummary>,
+    /// the units in front of the beds 2..n
+    pub inter_beds: Vec<InterBedResult>,
+    pub solver: Vec<BedSolverStats>,
+    pub total_length: f64,
+    /// [-] ammonia mole fraction at the outlet of the last bed
+    pub final_yield: f64,
+    /// [bar]
+    pub outlet_pressure: f64,
+    /// [°C] highest temperature in all beds
+    pub peak_temperature: f64,
+}
+
+impl HaberBoschInstance {
+    /// Summarizes the simulation results, None if the instance has not been simulated yet.
+    pub fn summary(&self) -> Option<SimulationSummary> {
+        let mut beds = vec![];
+        for (idx, res) in self.results().iter().enumerate() {
+            let (x_in, y_in) = (res.x_out.first()?, res.y_out.first()?);
+            let (x_out, y_out) = (res.x_out.last()?, res.y_out.last()?);
+
+            beds.push(BedSummary {
+                bed: idx as u32 + 1,
+                length: x_out - x_in,
+                t_in: y_in[5] - 273.,
+                t_out: y_out[5] - 273.,
+                nh3_in: nh3_fraction(y_in),
+                nh3_out: nh3_fraction(y_out),
+                conversion_n2: (y_in[0] - y_out[0]) / y_in[0],
+                conversion_h2: (y_in[1] - y_out[1]) / y_in[1],
+                pressure_out: y_out[6],
+            });
+        }
+
+        let last = self.results().last()?;
+        let last_bed = beds.last()?;
+        Some(SimulationSummary {
+            catalyst: self.cat().to_string(),
+            total_length: *last.x_out.last()?,
+            final_yield: last_bed.nh3_out,
+            outlet_pressure: last_bed.pressure_out,
+            peak_temperature: self
+                .results()
+                .iter()
+                .flat_map(|res| res.y_out.iter().map(|y| y[5] - 273.))
+                .fold(f64::NEG_INFINITY, f64::max),
+            inter_beds: self.inter_bed_results(),
+            solver: self.bed_stats(),
+            beds,
+        })
+    }
+}
+
+impl Display for SimulationSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Summary based on {}", self.catalyst)?;
+
+        let lengths: Vec<String> = self
+            .beds
+            .iter()
+            .map(|b| format!("{:.3}", b.length))
+            .collect();
+        writeln!(
+            f,
+            "Reactor Length: {} = {:.3}",
+            lengths.join(" + "),
+            self.total_length
+        )?;
+        writeln!(f, "Final Yield: {:.4}", self.final_yield)?;
+        writeln!(f, "Outlet Pressure: {:.3} bar", self.outlet_pressure)?;
+        writeln!(f, "Peak Temperature: {:.1} °C", self.peak_temperature)?;
+
+        writeln!(
+            f,
+            "{:>4} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "Bed", "Length", "T_in", "T_out", "NH3_in", "NH3_out", "X_N2", "X_H2"
+        )?;
+        for b in &self.beds {
+            writeln!(
+                f,
+                "{:>4} {:>8.3} {:>8.1} {:>8.1} {:>8.4} {:>8.4} {:>8.4} {:>8.4}",
+                b.bed,
+                b.length,
+                b.t_in,
+                b.t_out,
+                b.nh3_in,
+                b.nh3_out,
+                b.conversion_n2,
+                b.conversion_h2
+            )?;
+        }
+
+        for (idx, inlet) in self.inter_beds.iter().enumerate() {
+            writeln!(f, "Inter-bed {} -> {}: {}", idx + 1, idx + 2, inlet)?;
+        }
+        for (idx, stats) in self.solver.iter().enumerate() {
+            writeln!(f, "Solver Bed {}: {}", idx + 1, stats)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::sequential_simulation;
+    use crate::test_fixtures::kmir;
+
+    fn kmir_summary() -> (HaberBoschInstance, SimulationSummary) {
+        let mut inst = kmir(3);
+        assert!(inst.summary().is_none());
+        sequential_simulation(&mut inst, false).unwrap();
+        let summary = inst.summary().unwrap();
+        (inst, summary)
+    }
+
+    #[test]
+    fn summarizes_every_bed() {
+        let (inst, summary) = kmir_summary();
+        assert_eq!(summary.beds.len(), 3);
+        assert_eq!(summary.inter_beds.len(), 2);
+        assert_eq!(summary.solver.len(), 3);
+
+        for (idx, (bed, res)) in summary.beds.iter().zip(inst.results()).enumerate() {
+            assert_eq!(bed.bed, idx as u32 + 1);
+            assert_eq!(bed.length, res.x_out.last().unwrap() - res.x_out.first().unwrap());
+            assert_eq!(bed.nh3_out, nh3_fraction(res.y_out.last().unwrap()));
+            assert!(bed.nh3_out > bed.nh3_in);
+            for conversion in [bed.conversion_n2, bed.conversion_h2] {
+                assert!((0. ..=1.).contains(&conversion));
+            }
+        }
+
+        let lengths = summary.beds.iter().map(|bed| bed.length).sum::<f64>();
+        assert!((lengths - summary.total_length).abs() < 1e-9 * summary.total_length);
+        let last = inst.results().last().unwrap().y_out.last().unwrap();
+        assert_eq!(summary.final_yield, nh3_fraction(last));
+        assert_eq!(summary.outlet_pressure, last[6]);
+        assert!(summary.beds.iter().all(|bed| bed.t_out <= summary.peak_temperature));
+    }
+
+    #[test]
+    fn serializes_the_fields_of_the_results_table() {
+        let (_, summary) = kmir_summary();
+        let json = serde_json::to_value(&summary).unwrap();
+
+        // read by show_results in www/index.js
+        assert_eq!(json["catalyst"], summary.catalyst.as_str());
+        for field in ["total_length", "final_yield", "outlet_pressure"] {
+            assert!(json[field].is_f64(), "{}", field);
+        }
+        let bed = &json["beds"][0];
+        for field in [
+            "length",
+            "t_in",
+            "t_out",
+            "nh3_in",
+            "nh3_out",
+            "conversion_n2",
+            "conversion_h2",
+        ] {
+            assert!(bed[field].is_f64(), "{}", field);
+        }
+        assert_eq!(bed["bed"], 1);
+    }
+}
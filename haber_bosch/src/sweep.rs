@@ -0,0 +1,698 @@
+//! Systematic parameter sweeps over a reactor configuration.
+//!
+//! A [`Sweep`] starts from a [`ReactorConfig`] and varies the pressure, the bed temperatures, the
+//! number of beds and the catalyst. The points are either the full Cartesian product of all
+//! dimensions or a Latin hypercube sample of them. Every point is simulated and ends up as one
+//! row of a [`SweepTable`] which can be printed or exported as CSV.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use serde::Serialize;
+
+use crate::batch::{BatchProgress, BatchRunner};
+use crate::config::{CatalystConfig, ConfigError, ReactorConfig};
+use crate::configuration::HaberBoschInstance;
+use crate::export::ExportError;
+use crate::simulation::sequential_simulation;
+use crate::v2_hints::*;
+
+/// A parameter of the reactor configuration that can be varied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum SweepParameter {
+    /// [bar] pressure at the inlet of the first bed
+    Pressure,
+    TStart,
+    TSlope,
+    TMax,
+    Beta,
+    /// the number of beds, additional beds are copies of the last bed of the base configuration
+    BedCount,
+}
+
+impl SweepParameter {
+    /// True for the parameters that belong to a single bed.
+    pub fn is_bed_parameter(&self) -> bool {
+        matches!(
+            self,
+            SweepParameter::TStart
+                | SweepParameter::TSlope
+                | SweepParameter::TMax
+                | SweepParameter::Beta
+        )
+    }
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pressure" => Ok(SweepParameter::Pressure),
+            "t-start" | "t_start" => Ok(SweepParameter::TStart),
+            "t-slope" | "t_slope" => Ok(SweepParameter::TSlope),
+            "t-max" | "t_max" => Ok(SweepParameter::TMax),
+            "beta" => Ok(SweepParameter::Beta),
+            "beds" | "bed-count" | "bed_count" => Ok(SweepParameter::BedCount),
+            _ => Err("Unknown sweep parameter".to_owned()),
+        }
+    }
+}
+
+impl Display for SweepParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SweepParameter::Pressure => "pressure",
+            SweepParameter::TStart => "t_start",
+            SweepParameter::TSlope => "t_slope",
+            SweepParameter::TMax => "t_max",
+            SweepParameter::Beta => "beta",
+            SweepParameter::BedCount => "bed_count",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The values of one parameter, `steps` equidistant values from `min` to `max`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct SweepDimension {
+    pub parameter: SweepParameter,
+    /// the reactor bed, starting at 1, only used by the bed parameters
+    pub bed: usize,
+    pub min: f64,
+    pub max: f64,
+    /// number of values in a Cartesian sweep, ignored by Latin hypercube sampling
+    pub steps: usize,
+}
+
+impl SweepDimension {
+    pub fn new(parameter: SweepParameter, bed: usize, min: f64, max: f64, steps: usize) -> Self {
+        SweepDimension {
+            parameter,
+            bed,
+            min,
+            max,
+            steps,
+        }
+    }
+
+    /// The column name in the sweep table, e.g. `t_max[2]`.
+    pub fn name(&self) -> String {
+        if self.parameter.is_bed_parameter() {
+            format!("{}[{}]", self.parameter, self.bed)
+        } else {
+            self.parameter.to_string()
+        }
+    }
+
+    /// The value at the relative position `u` in [0, 1], the bed count is rounded.
+    pub fn value_at(&self, u: f64) -> f64 {
+        let value = self.min + (self.max - self.min) * u;
+        if self.parameter == SweepParameter::BedCount {
+            value.round()
+        } else {
+            value
+        }
+    }
+
+    /// The values of a Cartesian sweep.
+    pub fn values(&self) -> Vec<f64> {
+        if self.steps <= 1 {
+            return vec![self.value_at(0.)];
+        }
+        (0..self.steps)
+            .map(|step| self.value_at(step as f64 / (self.steps - 1) as f64))
+            .collect()
+    }
+}
+
+/// Parses `parameter[@bed]=min:max[:steps]`, e.g. `t-max@2=700:760:4` or `pressure=150:250`.
+impl FromStr for SweepDimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lhs, rhs) = s
+            .split_once('=')
+            .ok_or("Expected parameter[@bed]=min:max[:steps]")?;
+        let (parameter, bed) = match lhs.split_once('@') {
+            Some((parameter, bed)) => (
+                parameter,
+                bed.trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid bed '{}'", bed))?,
+            ),
+            None => (lhs, 1),
+        };
+
+        let parts: Vec<&str> = rhs.split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err("Expected min:max[:steps]".to_owned());
+        }
+        let number = |s: &str| {
+            s.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid number '{}'", s))
+        };
+        let steps = match parts.get(2) {
+            Some(steps) => steps
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid number of steps '{}'", steps))?,
+            None => 11,
+        };
+
+        Ok(SweepDimension::new(
+            parameter.parse()?,
+            bed,
+            number(parts[0])?,
+            number(parts[1])?,
+            steps,
+        ))
+    }
+}
+
+/// How the points of the sweep are chosen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Sampling {
+    /// every combination of the values of all dimensions
+    #[default]
+    Cartesian,
+    /// `samples` points, each dimension is split into `samples` strata and each stratum is hit
+    /// exactly once
+    LatinHypercube { samples: usize, seed: u64 },
+}
+
+/// The key figures of a simulated sweep point, temperatures are given in °C.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct SweepMetrics {
+    pub total_length: f64,
+    /// [-] ammonia mole fraction at the outlet of the last bed
+    pub final_yield: f64,
+    /// [°C] the highest temperature in all beds
+    pub max_temperature: f64,
+}
+
+impl SweepMetrics {
+    fn of(inst: &HaberBoschInstance) -> Option<Self> {
+        let summary = inst.summary()?;
+        Some(SweepMetrics {
+            total_length: summary.total_length,
+            final_yield: summary.final_yield,
+            max_temperature: summary.peak_temperature,
+        })
+    }
+}
+
+/// One simulated point of the sweep.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepRow {
+    pub catalyst: String,
+    /// the values of the dimensions in the order of [`SweepTable::columns`], see
+    /// [`SweepPoint::values`]
+    pub values: Vec<Option<f64>>,
+    /// the metrics or why the point could not be simulated
+    pub outcome: Result<SweepMetrics, String>,
+}
+
+/// The results of a sweep.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepTable {
+    /// the names of the swept dimensions
+    pub columns: Vec<String>,
+    pub rows: Vec<SweepRow>,
+}
+
+impl SweepTable {
+    /// The rows that could be simulated.
+    pub fn successful(&self) -> impl Iterator<Item = (&SweepRow, &SweepMetrics)> {
+        self.rows
+            .iter()
+            .filter_map(|row| row.outcome.as_ref().ok().map(|metrics| (row, metrics)))
+    }
+
+    /// Writes the table as CSV, failed points have empty metrics and an error message.
+    ///
+    /// Bed parameters of beds that do not exist at a point are left empty.
+    pub fn write_csv<W: std::io::Write>(&self, writer: W) -> Result<(), ExportError> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        let mut header = vec!["catalyst".to_owned()];
+        header.extend(self.columns.iter().cloned());
+        header.extend(
+            ["total_length", "final_yield", "max_temperature_c", "error"].map(str::to_owned),
+        );
+        wtr.write_record(&header)?;
+
+        for row in &self.rows {
+            let mut record = vec![row.catalyst.clone()];
+            record.extend(
+                row.values
+                    .iter()
+                    .map(|v| v.map_or_else(String::new, |v| v.to_string())),
+            );
+            match &row.outcome {
+                Ok(m) => record.extend([
+                    m.total_length.to_string(),
+                    m.final_yield.to_string(),
+                    m.max_temperature.to_string(),
+                    String::new(),
+                ]),
+                Err(e) => record.extend([String::new(), String::new(), String::new(), e.clone()]),
+            }
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let file = std::fs::File::create(path)?;
+        self.write_csv(file)
+    }
+}
+
+impl Display for SweepTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>10}", "Catalyst")?;
+        for col in &self.columns {
+            write!(f, " {:>12}", col)?;
+        }
+        writeln!(f, " {:>10} {:>10} {:>12}", "Length", "Yield", "T_max [°C]")?;
+
+        for row in &self.rows {
+            write!(f, "{:>10}", row.catalyst)?;
+            for value in &row.values {
+                match value {
+                    Some(value) => write!(f, " {:>12.3}", value)?,
+                    None => write!(f, " {:>12}", "-")?,
+                }
+            }
+            match &row.outcome {
+                Ok(m) => writeln!(
+                    f,
+                    " {:>10.3} {:>10.4} {:>12.1}",
+                    m.total_length, m.final_yield, m.max_temperature
+                )?,
+                Err(e) => writeln!(f, " {}", e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A sweep over a base configuration.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub base: ReactorConfig,
+    pub dimensions: Vec<SweepDimension>,
+    /// every point is simulated with each of these catalysts, only the catalyst of the base
+    /// configuration if empty
+    pub catalysts: Vec<CatalystConfig>,
+    pub sampling: Sampling,
+}
+
+/// A point of the sweep before it is simulated.
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub catalyst: String,
+    /// the values of the dimensions, None for a bed parameter whose bed does not exist with the
+    /// bed count of this point
+    pub values: Vec<Option<f64>>,
+    pub config: ReactorConfig,
+}
+
+impl Sweep {
+    pub fn new(base: ReactorConfig) -> Self {
+        Sweep {
+            base,
+            dimensions: vec![],
+            catalysts: vec![],
+            sampling: Sampling::Cartesian,
+        }
+    }
+
+    pub fn with_dimension(mut self, dimension: SweepDimension) -> Self {
+        self.dimensions.push(dimension);
+        self
+    }
+
+    pub fn with_catalyst(mut self, catalyst: CatalystConfig) -> Self {
+        self.catalysts.push(catalyst);
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Checks the dimensions against the base configuration.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field: String, message: &str| {
+            Err(ConfigError::Invalid {
+                field,
+                message: message.to_owned(),
+            })
+        };
+
+        let max_beds = self
+            .dimensions
+            .iter()
+            .filter(|dim| dim.parameter == SweepParameter::BedCount)
+            .map(|dim| dim.min.max(dim.max).round() as usize)
+            .fold(self.base.beds.len(), usize::max);
+
+        for (idx, dim) in self.dimensions.iter().enumerate() {
+            let field = |name: &str| format!("dimensions[{}].{}", idx, name);
+            if !(dim.min.is_finite() && dim.max.is_finite()) {
+                return invalid(field("min"), "the range has to be finite");
+            }
+            if dim.steps == 0 {
+                return invalid(field("steps"), "at least one step is required");
+            }
+            if dim.parameter.is_bed_parameter() && (dim.bed == 0 || dim.bed > max_beds) {
+                return invalid(field("bed"), "there is no such reactor bed");
+            }
+            if dim.parameter == SweepParameter::BedCount && dim.min.min(dim.max) < 1. {
+                return invalid(field("min"), "at least one reactor bed is required");
+            }
+            let count = dim.min.max(dim.max).round();
+            if dim.parameter == SweepParameter::BedCount && count > SWEEP_MAX_BEDS as f64 {
+                return invalid(
+                    field("max"),
+                    &format!("at most {} reactor beds are supported", SWEEP_MAX_BEDS),
+                );
+            }
+        }
+        if let Sampling::LatinHypercube { samples: 0, .. } = self.sampling {
+            return invalid("sampling.samples".to_owned(), "has to be positive");
+        }
+        if self.base.beds.is_empty() {
+            return invalid("beds".to_owned(), "at least one reactor bed is required");
+        }
+        Ok(())
+    }
+
+    /// The configurations of all points, the catalysts vary slowest.
+    pub fn points(&self) -> Result<Vec<SweepPoint>, ConfigError> {
+        self.validate()?;
+
+        let samples = match self.sampling {
+            Sampling::Cartesian => self.cartesian_samples(),
+            Sampling::LatinHypercube { samples, seed } => {
+                self.latin_hypercube_samples(samples, seed)
+            }
+        };
+        let catalysts = if self.catalysts.is_empty() {
+            vec![self.base.catalyst.clone()]
+        } else {
+            self.catalysts.clone()
+        };
+
+        let mut points = vec![];
+        for catalyst in catalysts {
+            let base = ReactorConfig {
+                catalyst,
+                ..self.base.clone()
+            };
+            let name = base.catalyst_kinetics()?.name;
+            for values in &samples {
+                let (config, values) = self.apply(&base, values);
+                points.push(SweepPoint {
+                    catalyst: name.clone(),
+                    values,
+                    config,
+                });
+            }
+        }
+        Ok(points)
+    }
+
+    /// Simulates all points, points that fail are kept in the table with their error.
+    pub fn run(&self) -> Result<SweepTable, ConfigError> {
+        self.run_with(&BatchRunner::new(), |_| {})
+    }
+
+    /// Like [`Sweep::run`] but the points are distributed by `runner`, the rows keep their order.
+    pub fn run_with<P>(&self, runner: &BatchRunner, progress: P) -> Result<SweepTable, ConfigError>
+    where
+        P: Fn(BatchProgress) + Sync,
+    {
+        let rows = runner.map(self.points()?, simulate_point, progress);
+        Ok(SweepTable {
+            columns: self.dimensions.iter().map(|dim| dim.name()).collect(),
+            rows,
+        })
+    }
+
+    fn cartesian_samples(&self) -> Vec<Vec<f64>> {
+        let mut samples = vec![vec![]];
+        for dim in &self.dimensions {
+            let values = dim.values();
+            samples = samples
+                .into_iter()
+                .flat_map(|sample| {
+                    values.iter().map(move |v| {
+                        let mut next = sample.clone();
+                        next.push(*v);
+                        next
+                    })
+                })
+                .collect();
+        }
+        samples
+    }
+
+    fn latin_hypercube_samples(&self, samples: usize, seed: u64) -> Vec<Vec<f64>> {
+        let mut rng = SplitMix64(seed);
+        let mut columns = vec![];
+        for dim in &self.dimensions {
+            // Fisher-Yates shuffle of the strata
+            let mut strata: Vec<usize> = (0..samples).collect();
+            for i in (1..samples).rev() {
+                strata.swap(i, rng.next_below(i + 1));
+            }
+            let column: Vec<f64> = strata
+                .into_iter()
+                .map(|stratum| dim.value_at((stratum as f64 + rng.next_f64()) / samples as f64))
+                .collect();
+            columns.push(column);
+        }
+
+        (0..samples)
+            .map(|idx| columns.iter().map(|col| col[idx]).collect())
+            .collect()
+    }
+
+    /// The base configuration with the values of one sample, the bed count is applied first.
+    ///
+    /// Returns the values that could be applied, None for the beds that do not exist.
+    fn apply(&self, base: &ReactorConfig, values: &[f64]) -> (ReactorConfig, Vec<Option<f64>>) {
+        let mut config = base.clone();
+        let dims = self.dimensions.iter().zip(values);
+
+        for (dim, value) in dims.clone() {
+            if dim.parameter == SweepParameter::BedCount {
+                let count = *value as usize;
+                let last = *config.beds.last().unwrap();
+                config.beds.resize(count, last);
+            }
+        }
+
+        let mut applied = vec![];
+        for (dim, value) in dims {
+            let bed = dim
+                .bed
+                .checked_sub(1)
+                .and_then(|idx| config.beds.get_mut(idx));
+            match (dim.parameter, bed) {
+                (SweepParameter::Pressure, _) => config.pressure = *value,
+                (SweepParameter::BedCount, _) => {}
+                (SweepParameter::TStart, Some(bed)) => bed.t_start = *value,
+                (SweepParameter::TSlope, Some(bed)) => bed.t_slope = *value,
+                (SweepParameter::TMax, Some(bed)) => bed.t_max = *value,
+                (SweepParameter::Beta, Some(bed)) => bed.beta = *value,
+                // the bed does not exist with the bed count of this point
+                _ => {
+                    applied.push(None);
+                    continue;
+                }
+            }
+            applied.push(Some(*value));
+        }
+        (config, applied)
+    }
+}
+
+/// Simulates a single point of a sweep.
+pub fn simulate_point(point: SweepPoint) -> SweepRow {
+    let outcome = point
+        .config
+        .build()
+        .map_err(|e| e.to_string())
+        .and_then(|mut inst| {
+            sequential_simulation(&mut inst, false).map_err(|e| e.to_string())?;
+            SweepMetrics::of(&inst).ok_or_else(|| "no simulation results".to_owned())
+        });
+
+    SweepRow {
+        catalyst: point.catalyst,
+        values: point.values,
+        outcome,
+    }
+}
+
+/// A small deterministic random number generator, good enough to place the sample points.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    fn kmir() -> ReactorConfig {
+        ReactorConfig::from_instance(&test_fixtures::kmir(2))
+    }
+
+    fn dim(s: &str) -> SweepDimension {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_dimensions() {
+        assert_eq!(
+            dim("t-max@2=700:760:4"),
+            SweepDimension::new(SweepParameter::TMax, 2, 700., 760., 4)
+        );
+        assert_eq!(
+            dim(" pressure = 150 : 250 "),
+            SweepDimension::new(SweepParameter::Pressure, 1, 150., 250., 11)
+        );
+        assert_eq!(dim("beds=1:3:3").parameter, SweepParameter::BedCount);
+
+        for invalid in ["t-max", "t-max=700", "t-max=1:2:3:4", "t-max@x=1:2", "foo=1:2"] {
+            assert!(invalid.parse::<SweepDimension>().is_err(), "{}", invalid);
+        }
+        assert!("t-max=a:2".parse::<SweepDimension>().is_err());
+        assert!("t-max=1:2:x".parse::<SweepDimension>().is_err());
+    }
+
+    #[test]
+    fn cartesian_grid_varies_the_last_dimension_fastest() {
+        let points = Sweep::new(kmir())
+            .with_dimension(dim("pressure=150:250:3"))
+            .with_dimension(dim("t-start@2=650:700:2"))
+            .points()
+            .unwrap();
+
+        let expected = [
+            [150., 650.],
+            [150., 700.],
+            [200., 650.],
+            [200., 700.],
+            [250., 650.],
+            [250., 700.],
+        ];
+        assert_eq!(points.len(), expected.len());
+        for (point, expected) in points.iter().zip(expected) {
+            assert_eq!(point.values, expected.map(Some));
+            assert_eq!(point.config.pressure, expected[0]);
+            assert_eq!(point.config.beds[1].t_start, expected[1]);
+            assert_eq!(point.config.beds[0].t_start, KMIR_B1_TS);
+        }
+    }
+
+    #[test]
+    fn latin_hypercube_hits_every_stratum_once() {
+        let samples = 10;
+        let sweep = Sweep::new(kmir())
+            .with_dimension(dim("pressure=150:250"))
+            .with_dimension(dim("t-max@1=700:760"))
+            .with_sampling(Sampling::LatinHypercube { samples, seed: 7 });
+        let points = sweep.points().unwrap();
+        assert_eq!(points.len(), samples);
+
+        for (idx, dim) in sweep.dimensions.iter().enumerate() {
+            let mut strata: Vec<usize> = points
+                .iter()
+                .map(|p| {
+                    let u = (p.values[idx].unwrap() - dim.min) / (dim.max - dim.min);
+                    (u * samples as f64) as usize
+                })
+                .collect();
+            strata.sort();
+            assert_eq!(strata, (0..samples).collect::<Vec<_>>());
+        }
+
+        // the same seed gives the same points
+        let again = sweep.points().unwrap();
+        assert!(points.iter().zip(&again).all(|(a, b)| a.values == b.values));
+    }
+
+    #[test]
+    fn leaves_out_parameters_of_missing_beds() {
+        let sweep = Sweep::new(kmir())
+            .with_dimension(dim("t-start@3=680:740:3"))
+            .with_dimension(dim("beds=1:3:3"));
+        let points = sweep.points().unwrap();
+        assert_eq!(points.len(), 9);
+
+        for point in &points {
+            let beds = point.values[1].unwrap() as usize;
+            assert_eq!(point.config.beds.len(), beds);
+            if beds < 3 {
+                assert_eq!(point.values[0], None);
+            } else {
+                assert_eq!(point.config.beds[2].t_start, point.values[0].unwrap());
+            }
+        }
+
+        let table = SweepTable {
+            columns: sweep.dimensions.iter().map(|dim| dim.name()).collect(),
+            rows: points
+                .into_iter()
+                .map(|point| SweepRow {
+                    catalyst: point.catalyst,
+                    values: point.values,
+                    outcome: Err("not simulated".to_owned()),
+                })
+                .collect(),
+        };
+        let mut csv = vec![];
+        table.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.lines().nth(1).unwrap().starts_with("KMIR,,1,"));
+    }
+
+    #[test]
+    fn rejects_too_many_beds() {
+        let beds = format!("beds=1:{}:2", SWEEP_MAX_BEDS);
+        assert!(Sweep::new(kmir()).with_dimension(dim(&beds)).validate().is_ok());
+
+        for beds in [format!("beds=1:{}:2", SWEEP_MAX_BEDS + 1), "beds=1e12:1:2".to_owned()] {
+            let res = Sweep::new(kmir()).with_dimension(dim(&beds)).validate();
+            let field = match res {
+                Err(ConfigError::Invalid { field, .. }) => field,
+                other => panic!("{} was accepted: {:?}", beds, other),
+            };
+            assert_eq!(field, "dimensions[0].max");
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Reactors shared by the unit tests of several modules.
+
+use crate::configuration::{Catalyst, HaberBoschInstance, HaberBoschInstanceBuilder};
+use crate::simulation::sequential_simulation;
+use crate::v2_hints::*;
+
+/// The first `beds` reactor beds of the KMIR case study.
+pub(crate) fn kmir_builder(beds: usize) -> HaberBoschInstanceBuilder {
+    let constants = [
+        (KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B),
+        (KMIR_B2_TS, KMIR_B2_TR, KMIR_B2_TM, KMIR_B2_B),
+        (KMIR_B3_TS, KMIR_B3_TR, KMIR_B3_TM, KMIR_B3_B),
+    ];
+    assert!(beds <= constants.len(), "the KMIR case study has {} beds", constants.len());
+
+    constants.iter().take(beds).fold(
+        HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, Catalyst::KMIR),
+        |builder, &(t_start, t_slope, t_max, beta)| builder.add_bed(t_start, t_slope, t_max, beta),
+    )
+}
+
+/// The KMIR case study with `beds` beds, not simulated yet.
+pub(crate) fn kmir(beds: usize) -> HaberBoschInstance {
+    kmir_builder(beds).build().unwrap()
+}
+
+/// The KMIR case study with `beds` beds and its results.
+pub(crate) fn simulated_kmir(beds: usize) -> HaberBoschInstance {
+    let mut inst = kmir(beds);
+    sequential_simulation(&mut inst, false).unwrap();
+    inst
+}
@@ -1,10 +1,55 @@
 // Solver specific
 pub const AMMONIA_THRESHOLD: f64 = 0.001;
+pub const MIN_PARTIAL_PRESSURE: f64 = 1e-9; // [bar] lower bound of the H2 and NH3 pressures in the rate expression, seeds feeds without ammonia
+pub const ROSENBROCK_MIN_RTOL: f64 = 1e-6; // tightest relative tolerance the stiff solver accepts
+pub const ROSENBROCK_MIN_ATOL: f64 = 1e-9; // tightest absolute tolerance the stiff solver accepts
+pub const T_MAX_TOLERANCE: f64 = 0.01; // [K] the ramp oscillates around t_max, within this distance it counts as reached
+pub const SOLOUT_CHUNK_STEPS: usize = 10; // output steps integrated at once by the solvers without solout()
+
+// Feed constants
+pub const FEED_MOLE_FRACTIONS: [f64; 5] = [0.2391, 0.623, 0.0413, 0.0793, 0.0172]; // N2, H2, NH3, Ar, CH4
+pub const FEED_SUM_TOLERANCE: f64 = 1e-3; // [-] allowed deviation of the sum of the mole fractions from one
+pub const FEED_MOLAR_FLOW: f64 = 3000.; // [mol/s] molar flow entering the first bed
+
+// Recycle loop constants
+pub const MAKEUP_MOLE_FRACTIONS: [f64; 5] = [0.2475, 0.7425, 0., 0.003, 0.007]; // N2, H2, NH3, Ar, CH4
+pub const MAKEUP_MOLAR_FLOW: f64 = 900.; // [mol/s] fresh syngas fed into the loop
+pub const SEPARATOR_NH3_RECOVERY: f64 = 0.85; // [-] fraction of ammonia condensed in the separator
+pub const PURGE_FRACTION: f64 = 0.02; // [-] fraction of the separator gas that is purged
+pub const RECYCLE_TOLERANCE: f64 = 1e-7; // [-] convergence limit of the converter feed mole fractions
+pub const RECYCLE_MAX_ITERATIONS: u32 = 200;
+pub const WEGSTEIN_INTERVAL: u32 = 4; // every fourth pass of the loop is accelerated, the others estimate the slope
+pub const WEGSTEIN_MIN_FACTOR: f64 = -50.; // [-] bounds of the acceleration factor of the recycle loop
+pub const WEGSTEIN_MAX_FACTOR: f64 = 0.;
+
+// Optimizer constants
+pub const OPTIMIZER_MAX_EVALUATIONS: usize = 400;
+pub const OPTIMIZER_TOLERANCE: f64 = 1e-6; // [-] spread of the objective values in the simplex
+pub const OPTIMIZER_INITIAL_STEP: f64 = 0.1; // [-] size of the start simplex relative to the bounds
+pub const OPTIMIZER_BOUND_TOLERANCE: f64 = 1e-6; // [-] closer to a bound (relative to the bounds) counts as on it
+pub const OPTIMIZER_MIN_RAMP: f64 = 1.; // [K°] t_max is at least this much above t_start
+pub const OPTIMIZER_LENGTH_PENALTY: f64 = 1.; // [1/m] objective penalty per length above the limit
+pub const OPTIMIZER_BED_T_SLOPE: f64 = 10.; // [K°] raise of temperature of generated beds
+pub const OPTIMIZER_BED_BETA: f64 = -2.708; // beta of generated beds
+
+// Sweep constants
+pub const SWEEP_MAX_BEDS: usize = 10; // upper bound of the bed count dimension, added beds copy the last one
+
+// Energy balance constants
+pub const HEAT_OF_REACTION: f64 = -11040.; // [cal/mol] heat of reaction per mol ammonia formed
+pub const HEAT_CAPACITIES: [f64; 5] = [7.35, 7.04, 11.0, 4.97, 13.0]; // [cal/(mol·K)] of N2, H2, NH3, Ar, CH4
+
+// Pressure drop constants
+pub const R_SI: f64 = 8.314; // [J/(mol·K)] gas constant in SI units for the ideal gas law
+pub const GAS_VISCOSITY: f64 = 3.0e-5; // [Pa·s] dynamic viscosity of the synthesis gas
+pub const MOLAR_MASSES: [f64; 5] = [0.028, 0.002, 0.017, 0.040, 0.016]; // [kg/mol] of N2, H2, NH3, Ar, CH4
 
 // Catalyst FN Constants
 pub const FN_EA: f64 = 38007.; // Activation energy
 pub const FN_BIG_A: f64 = 7.6683e+15; // Pre exponential factor
 pub const FN_ALPHA: f64 = 0.4;
+pub const FN_T_MIN: f64 = 300. + 273.; // [K°] lower bound of the valid temperature window
+pub const FN_T_MAX: f64 = 490. + 273.; // [K°] upper bound of the valid temperature window
 
 pub const FN_REACTOR_PRESSURE: f64 = 100.; // pressure inside the reactor for FN case study
 
@@ -24,6 +69,8 @@ pub const FN_B2_B: f64 = -2.708; // beta
 pub const KMIR_EA: f64 = 40131.; // Activation energy
 pub const KMIR_BIG_A: f64 = 1.6066e+15; // Pre exponential factor
 pub const KMIR_ALPHA: f64 = 0.5;
+pub const KMIR_T_MIN: f64 = 350. + 273.; // [K°] lower bound of the valid temperature window
+pub const KMIR_T_MAX: f64 = 490. + 273.; // [K°] upper bound of the valid temperature window
 
 pub const KMIR_REACTOR_PRESSURE: f64 = 200.; // pressure inside the reactor for KMIR case study
 
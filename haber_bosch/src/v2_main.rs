@@ -1,16 +1,30 @@
 #![allow(unreachable_code)]
 #![allow(unused)]
-use crate::{configuration::{Catalyst, HaberBoschInstanceBuilder}, simulation::sequential_simulation, visualization::prepare_chart};
+use std::process::ExitCode;
 
-use plotters::prelude::*;
+use clap::Parser;
 
 pub mod simulation;
+pub mod rosenbrock;
+pub mod batch;
+pub mod chart;
+pub mod config;
 pub mod configuration;
+pub mod diagnostics;
+pub mod difference;
+pub mod equilibrium;
+pub mod export;
+pub mod optimization;
+pub mod recycle;
+pub mod summary;
+pub mod sweep;
 pub mod visualization;
+pub mod cli;
+#[cfg(test)]
+mod test_fixtures;
 
-pub mod v2_hints; use plotters::element::BitMapElement;
 // this contains our model constants
-use v2_hints::*;
+pub mod v2_hints;
 
 
 // Reactor bed related constants:
@@ -20,43 +34,9 @@ use v2_hints::*;
 // FN_B2_TS, FN_B2_TR, FN_B2_TM, FN_B2_B
 
 
-fn main() -> Result<(), Box<dyn std::error::Error>>
+fn main() -> ExitCode
 {
-    
-    println!("Hello sequential Simulation!");
-    // case studies with different catalysts:
-    let studies = [(Catalyst::KMIR, "HB_KMIR"), (Catalyst::FN, "HB_FN")]; 
-    for (cat, fn_pref) in studies
-    {
-        // configure Haber-Bosch case-study (mostly module configuration in configuration.rs)
-        let mut conf = match cat {
-            Catalyst::KMIR => {
-                HaberBoschInstanceBuilder::create(KMIR_REACTOR_PRESSURE, cat)
-                    .add_bed(KMIR_B1_TS, KMIR_B1_TR, KMIR_B1_TM, KMIR_B1_B)
-                    .add_bed(KMIR_B2_TS, KMIR_B2_TR, KMIR_B2_TM, KMIR_B2_B)
-                    .build()
-            }
-            Catalyst::FN => {
-                HaberBoschInstanceBuilder::create(FN_REACTOR_PRESSURE, cat)
-                    .add_bed(FN_B1_TS, FN_B1_TR, FN_B1_TM, FN_B1_B)
-                    .add_bed(FN_B2_TS, FN_B2_TR, FN_B2_TM, FN_B2_B)
-                    .build()
-            },
-        };
-
-        println!("{:?}", conf);
-
-        // simulate Haber-Bosch case-study (mostly module simulation in simulation.rs)
-        sequential_simulation(&mut conf, false);
-        conf.print_summary();
-
-        // visualize Haber-Bosch case-study (feed module visualization (visualization.rs) from configuration)
-        let fn_conc = fn_pref.to_owned() + "_conc.png";
-        visualization::draw_concentations(&fn_conc.as_str(), &conf)?;
-
-        let fn_temp_over_yield = fn_pref.to_owned() + "_temp_yield.png";
-        visualization::draw_temperature_over_yield(fn_temp_over_yield.as_str(), &conf)?;
-    }
-
-    Ok(())
-}
\ No newline at end of file
+    // run `hb_seq_simulation --help` for the subcommands, without one the case studies of the
+    // video series are simulated with their recycle loops and plotted (see cli.rs)
+    cli::run(cli::Cli::parse())
+}
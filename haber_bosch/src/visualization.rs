@@ -1,212 +1,1030 @@
-use std::ops::Range;
+use std::{error::Error, ops::Range, path::Path};
 
 use plotters::{
-    chart::DualCoordChartContext,
     coord::{types::RangedCoordf32, Shift},
-    prelude::*, style::full_palette::GREY_100,
+    element::{Drawable, IntoDynElement, PointCollection},
+    prelude::*,
+    style::{
+        full_palette::GREY_100,
+        text_anchor::{HPos, Pos, VPos},
+    },
 };
+use plotters_backend::{BackendCoord, DrawingErrorKind};
+
+use wasm_bindgen::prelude::*;
+
+use crate::chart::{prepare_chart, prepare_dual_chart};
 
 use crate::configuration::HaberBoschInstance;
 
+/// Additional curves of the temperature over yield plots.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CurveOverlay {
+    /// NH3 fraction in equilibrium over the temperature
+    pub equilibrium: bool,
+    /// temperature with the highest formation rate over the NH3 fraction
+    pub max_rate: bool,
+}
+
+#[wasm_bindgen]
+impl CurveOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for CurveOverlay {
+    fn default() -> Self {
+        CurveOverlay {
+            equilibrium: true,
+            max_rate: false,
+        }
+    }
+}
+
+/// Axis ranges of a plot: length, concentration and temperature in °C.
+pub type AxisRanges = (Range<f32>, Range<f32>, Range<f32>);
+
+/// Settings of the renderers that are the same for all backends.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlotStyle {
+    /// taken from the results of all scenarios if None
+    pub ranges: Option<AxisRanges>,
+    /// curves drawn on top of the temperature over yield plot of the first scenario
+    pub overlay: CurveOverlay,
+}
+
+impl PlotStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ranges(mut self, ranges: Option<AxisRanges>) -> Self {
+        self.ranges = ranges;
+        self
+    }
+
+    pub fn with_overlay(mut self, overlay: CurveOverlay) -> Self {
+        self.overlay = overlay;
+        self
+    }
+}
+
+/// Number of points of the equilibrium and maximum rate curves.
+const OVERLAY_STEPS: usize = 200;
+
+/// Draws the curves of `overlay` for `conf` into a temperature over yield chart, points outside
+/// of the axis ranges are left out. The curves are labelled, the legend is left to the caller.
+pub fn draw_overlay<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContext<'a, DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    conf: &HaberBoschInstance,
+    overlay: CurveOverlay,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (x_range, t_range) = (chart.x_range(), chart.y_range());
+    let inside = |(x, t): &(f32, f32)| x_range.contains(x) && t_range.contains(t);
+    // the curves are calculated in K and plotted in °C
+    let to_plot = |(t, nh3): (f64, f64)| (nh3 as f32, t as f32 - 273f32);
+
+    if overlay.equilibrium {
+        let temperatures = (t_range.start as f64 + 273.)..=(t_range.end as f64 + 273.);
+        let points: Vec<_> = conf.equilibrium_curve(temperatures, OVERLAY_STEPS)
+            .into_iter()
+            .map(to_plot)
+            .filter(inside)
+            .collect();
+        chart
+            .draw_series(LineSeries::new(points, &BLUE))?
+            .label("Equilibrium")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+    }
+
+    if overlay.max_rate {
+        let fractions = conf.feed().nh3()..=(x_range.end as f64);
+        let points: Vec<_> = conf.max_rate_curve(fractions, OVERLAY_STEPS)
+            .into_iter()
+            .map(to_plot)
+            .filter(inside)
+            .collect();
+        chart
+            .draw_series(LineSeries::new(points, &RED))?
+            .label("Maximum Rate")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+    }
 
-/// Place new functions here:
+    Ok(())
+}
+
+
+/// File formats of the plots written by [`draw_concentations`] and
+/// [`draw_temperature_over_yield`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlotFormat {
+    /// png, jpg or bmp, plotters picks the encoder by the extension
+    Bitmap,
+    Svg,
+    /// converted from the SVG output, requires the `pdf` feature
+    Pdf,
+}
+
+impl PlotFormat {
+    /// The format for the extension of `filename`, None if it is not supported.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let ext = Path::new(filename).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "png" | "jpg" | "jpeg" | "bmp" => Some(PlotFormat::Bitmap),
+            "svg" => Some(PlotFormat::Svg),
+            "pdf" => Some(PlotFormat::Pdf),
+            _ => None,
+        }
+    }
+}
+
+/// Size and format of the plots written to files.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlotSettings {
+    /// [px] width and height of the bitmap or the SVG canvas
+    pub resolution: (u32, u32),
+    /// [px/inch] size of a pixel on paper, gives the page size of PDF files
+    pub dpi: u32,
+    /// taken from the extension of the file name if None
+    pub format: Option<PlotFormat>,
+}
+
+impl PlotSettings {
+    pub fn new(resolution: (u32, u32)) -> Self {
+        PlotSettings {
+            resolution,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_dpi(mut self, dpi: u32) -> Self {
+        self.dpi = dpi;
+        self
+    }
+
+    pub fn with_format(mut self, format: PlotFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+}
+
+impl Default for PlotSettings {
+    fn default() -> Self {
+        PlotSettings {
+            resolution: (1920, 1080),
+            dpi: 96,
+            format: None,
+        }
+    }
+}
+
+/// A plot that can be drawn on any backend, see [`render`].
+#[cfg(not(target_arch="wasm32"))]
+trait Figure {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static;
+}
+
+/// Draws `figure` into `filename` with the backend for the format of `settings`.
 #[cfg(not(target_arch="wasm32"))]
-pub fn draw_temperature_over_yield(filename: &str, conf: &HaberBoschInstance) -> Result<(), Box<dyn std::error::Error>> {
-    let resolution = (1920, 1080); 
-    let mut draw_area = BitMapBackend::new(
-        filename, 
-        resolution)
-        .into_drawing_area();
-    draw_area.fill(&WHITE);
-
-    let max_ammonia = conf.iter_my(2, true)
-        .map(|(x, a)| a)
-        .max_by(|lhs, rhs| {
-            lhs.partial_cmp(rhs).unwrap()
-         }).unwrap();   
+fn render(filename: &str, settings: &PlotSettings, figure: &impl Figure) -> Result<(), Box<dyn Error>> {
+    let format = settings
+        .format
+        .or_else(|| PlotFormat::from_filename(filename))
+        .ok_or_else(|| {
+            format!("Unknown plot format of {}, use png, jpg, bmp, svg or pdf", filename)
+        })?;
+
+    match format {
+        PlotFormat::Bitmap => {
+            let area = BitMapBackend::new(filename, settings.resolution).into_drawing_area();
+            figure.draw(&area)?;
+            area.present()?;
+        }
+        PlotFormat::Svg => {
+            let area = SVGBackend::new(filename, settings.resolution).into_drawing_area();
+            figure.draw(&area)?;
+            area.present()?;
+        }
+        PlotFormat::Pdf => {
+            if settings.dpi == 0 {
+                return Err(format!("Invalid dpi {}, it has to be positive", settings.dpi).into());
+            }
+            let mut svg = String::new();
+            {
+                let area = SVGBackend::with_string(&mut svg, settings.resolution).into_drawing_area();
+                figure.draw(&area)?;
+                area.present()?;
+            }
+            std::fs::write(filename, svg_to_pdf(&svg, settings.dpi)?)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "pdf", not(target_arch="wasm32")))]
+fn svg_to_pdf(svg: &str, dpi: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    use svg2pdf::usvg::{fontdb, PostProcessingSteps, Tree, TreeParsing, TreePostProc};
+
+    let mut tree = Tree::from_str(svg, &Default::default())?;
+    // the text has to be converted to paths, it is left out of the PDF otherwise
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+    // plotters asks for sans-serif, which fontdb maps to Arial, missing on most Linux systems
+    let sans_serif = fontdb::Query {
+        families: &[fontdb::Family::SansSerif],
+        ..Default::default()
+    };
+    if fonts.query(&sans_serif).is_none() {
+        let families: Vec<_> = fonts.faces().flat_map(|face| &face.families).collect();
+        let fallback = families
+            .iter()
+            .find(|(name, _)| name.contains("Sans"))
+            .or(families.first())
+            .map(|(name, _)| name.clone());
+        if let Some(name) = fallback {
+            fonts.set_sans_serif_family(name);
+        }
+    }
+    tree.postprocess(PostProcessingSteps::default(), &fonts);
+
+    let options = svg2pdf::Options {
+        dpi: dpi as f32,
+        ..Default::default()
+    };
+    Ok(svg2pdf::convert_tree(&tree, options))
+}
+
+#[cfg(all(not(feature = "pdf"), not(target_arch="wasm32")))]
+fn svg_to_pdf(_svg: &str, _dpi: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("PDF output requires the pdf feature".into())
+}
+
+#[cfg(not(target_arch="wasm32"))]
+struct TemperatureOverYield<'a> {
+    scenarios: &'a [Scenario<'a>],
+    style: &'a PlotStyle,
+}
+
+#[cfg(not(target_arch="wasm32"))]
+impl Figure for TemperatureOverYield<'_> {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw_temperature_over_yield_on(area, self.scenarios, self.style).map(|_| ())
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+struct Concentrations<'a> {
+    scenarios: &'a [Scenario<'a>],
+    style: &'a PlotStyle,
+}
+
+#[cfg(not(target_arch="wasm32"))]
+impl Figure for Concentrations<'_> {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw_concentations_on(area, self.scenarios, self.style).map(|_| ())
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+struct Difference<'a> {
+    base: &'a Scenario<'a>,
+    other: &'a Scenario<'a>,
+    style: &'a PlotStyle,
+}
+
+#[cfg(not(target_arch="wasm32"))]
+impl Figure for Difference<'_> {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw_difference_on(area, self.base, self.other, self.style).map(|_| ())
+    }
+}
+
+/// Writes the temperature over yield plot, the format is given by `settings` or the extension.
+#[cfg(not(target_arch="wasm32"))]
+pub fn draw_temperature_over_yield(filename: &str, scenarios: &[Scenario], settings: &PlotSettings, style: &PlotStyle) -> Result<(), Box<dyn Error>> {
+    render(filename, settings, &TemperatureOverYield { scenarios, style })
+}
+
+/// Draws the temperature over yield plot of all scenarios on any plotters backend, the overlay
+/// belongs to the first one.
+///
+/// Returns the conversion from backend to chart coordinates.
+#[allow(clippy::type_complexity)]
+pub fn draw_temperature_over_yield_on<DB: DrawingBackend>(
+    draw_area: &DrawingArea<DB, Shift>,
+    scenarios: &[Scenario],
+    style: &PlotStyle,
+) -> Result<impl Fn((i32, i32)) -> Option<(f32, f32)>, Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    check_scenarios(scenarios)?;
+    draw_area.fill(&WHITE)?;
+
+    let (ammonia_range, temp_range) = match &style.ranges {
+        Some((_, conc_range, temp_range)) => (0f32..conc_range.end, temp_range.clone()),
+        None => {
+            let max_ammonia = scenarios
+                .iter()
+                .flat_map(|s| s.instance.iter_my(2, true))
+                .map(|(_, a)| a)
+                .max_by(|lhs, rhs| lhs.total_cmp(rhs))
+                .unwrap_or(1f32);
+            (0f32..max_ammonia, axis_ranges(scenarios, style).2)
+        }
+    };
+    let scale = (range_len(&ammonia_range), range_len(&temp_range));
 
     // Function from homework (refactoring prepare_chart function in Part 1)
-    let mut chart = prepare_chart(&draw_area, 
-        format!("Haber-Bosch Temperature over Ammonia Yield with {}", conf.cat().to_string()).as_str(), 
-        ("concentration as Partial Fraction", "Temperature"),
-        0f32..max_ammonia, conf.get_temperature_range(), true);
+    let mut chart = prepare_chart(
+        draw_area,
+        &caption("Haber-Bosch Temperature over Ammonia Yield", scenarios),
+        ("Concentration as Partial Fraction", "Temperature"),
+        ammonia_range,
+        temp_range,
+        true,
+    );
 
-    let it_ammonia = conf.iter_my(2, true)
-        .map(|pair| pair.1);
-    let it_temperature = conf.iter_my(5, false)
-        .map(|(x, t)| t);
+    for (idx, scenario) in scenarios.iter().enumerate() {
+        let line = ScenarioLine::new(idx);
+        let it_ammonia = scenario.instance.iter_my(2, true).map(|pair| pair.1);
+        let it_temperature = scenario.instance.iter_my(5, false).map(|(_, t)| t);
+        let points: Vec<_> = it_ammonia.zip(it_temperature).collect();
+        let color = line.color;
 
-    chart
-        .draw_series(LineSeries::new(it_ammonia.zip(it_temperature), &BLACK))?
-        .label("Zig Zag")
-        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLACK));
+        chart
+            .draw_series(line.elements(points, color, scale))?
+            .label(&scenario.label)
+            .legend(move |(x, y)| line.legend(color, (x, y)));
+    }
+    draw_overlay(&mut chart, scenarios[0].instance, style.overlay)?;
 
-    draw_area.present()?;
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
 
-    Ok({})
+    Ok(chart.into_coord_trans())
 }
 
+/// Writes the concentration plot, the format is given by `settings` or the extension.
 #[cfg(not(target_arch="wasm32"))]
 pub fn draw_concentations(
     filename: &str,
-    inst: &HaberBoschInstance,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // @todo refactor into functions for part 1 plotting
-    let resolution = (1920, 1080);
+    scenarios: &[Scenario],
+    settings: &PlotSettings,
+    style: &PlotStyle,
+) -> Result<(), Box<dyn Error>> {
+    render(filename, settings, &Concentrations { scenarios, style })
+}
 
-    let draw_area = BitMapBackend::new(filename, resolution).into_drawing_area();
+/// Draws the concentration plot of all scenarios on any plotters backend.
+///
+/// The colour tells the component and the line pattern the scenario, so the legend lists the
+/// components once and then the patterns of the scenarios. The beds of the first scenario are
+/// shaded, the bed ends of all scenarios are drawn in their patterns and annotated.
+///
+/// Returns the conversions from backend to chart coordinates of the concentration and the
+/// temperature axis.
+#[allow(clippy::type_complexity)]
+pub fn draw_concentations_on<DB: DrawingBackend>(
+    draw_area: &DrawingArea<DB, Shift>,
+    scenarios: &[Scenario],
+    style: &PlotStyle,
+) -> Result<
+    (
+        impl Fn((i32, i32)) -> Option<(f32, f32)>,
+        impl Fn((i32, i32)) -> Option<(f32, f32)>,
+    ),
+    Box<dyn Error>,
+>
+where
+    DB::ErrorType: 'static,
+{
+    check_scenarios(scenarios)?;
     draw_area.fill(&WHITE)?;
 
+    let (x_range, conc_range, temp_range) = axis_ranges(scenarios, style);
+    let scale = (range_len(&x_range), range_len(&conc_range));
+    let temp_scale = (range_len(&x_range), range_len(&temp_range));
     let mut chart = prepare_dual_chart(
-        &draw_area,
-        format!(
-            "Haber-Bosch Concentration Balances over Length with {}",
-            inst.cat().to_string()
-        )
-        .as_ref(),
+        draw_area,
+        &caption("Haber-Bosch Concentration Balances over Length", scenarios),
         ("Length Indicator", "Concentration as partial Fractions"),
-        inst.get_x_range(),
-        inst.get_concentration_range(),
-        inst.get_temperature_range(),
+        x_range,
+        conc_range,
+        temp_range,
         "Temperature [°C]",
     );
 
+    draw_beds(&mut chart, scenarios)?;
+
     let colors = [RED, BLUE, GREEN];
     let labels = ["Nitrogen", "Hydrogen", "Ammonia"];
+    for (idx, scenario) in scenarios.iter().enumerate() {
+        let line = ScenarioLine::new(idx);
+        // the first scenario carries the labels of the components
+        let label = |name: &str| (idx == 0).then(|| name.to_owned());
+
+        for (comp, (color, name)) in colors.into_iter().zip(labels).enumerate() {
+            let color = line.shaded(color);
+            let points = scenario.instance.iter_my(comp, true).collect();
+            let series = chart.draw_series(line.elements(points, color, scale))?;
+            if let Some(label) = label(name) {
+                series.label(label).legend(legend_line(color));
+            }
+        }
 
-    for idx in 0..=2 {
-        let style = ShapeStyle {
-            color: colors[idx].to_rgba(),
-            filled: false,
-            stroke_width: 1,
+        let color = line.shaded(BLACK);
+        let points = scenario.instance.iter_my(5, false).collect();
+        let series = chart.draw_secondary_series(line.elements(points, color, temp_scale))?;
+        if let Some(label) = label("Temperature [C°]") {
+            series.label(label).legend(legend_line(color));
+        }
+    }
+
+    // one entry per scenario with its line pattern
+    if scenarios.len() > 1 {
+        for (idx, scenario) in scenarios.iter().enumerate() {
+            let line = ScenarioLine::new(idx);
+            let color = line.shaded(BLACK);
+            chart
+                .draw_series(std::iter::empty::<PathElement<(f32, f32)>>())?
+                .label(&scenario.label)
+                .legend(move |(x, y)| line.legend(color, (x, y)));
+        }
+    }
+
+    chart
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(GREY_100)
+        .position(SeriesLabelPosition::UpperRight)
+        .label_font(("sans-serif", 24).into_font())
+        .draw()?;
+
+    Ok(chart.into_coord_trans_pair())
+}
+
+/// The lengths at the start and the end of the simulated beds of `inst`.
+fn bed_extents(inst: &HaberBoschInstance) -> Vec<(f32, f32)> {
+    inst.results()
+        .iter()
+        .filter_map(|res| Some((*res.x_out.first()? as f32, *res.x_out.last()? as f32)))
+        .collect()
+}
+
+/// Rows of text of a bed annotation.
+const BED_ANNOTATION_ROWS: usize = 3;
+
+/// Shades every second bed of the first scenario over the full height of a chart with the length
+/// on the x axis and draws a line in the pattern of the scenario at the end of each bed.
+///
+/// The length and outlet yield of each bed are written into its upper left corner, the
+/// annotations of later scenarios below the ones of the first. Beds outside of the length axis
+/// are left out.
+pub fn draw_beds<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContext<'a, DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    scenarios: &[Scenario],
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (x_range, y_range) = (chart.x_range(), chart.y_range());
+    let scale = (range_len(&x_range), range_len(&y_range));
+    // the text hangs down from an anchor just inside of the upper left corner of the bed
+    let margin = (0.004 * scale.0, 0.01 * scale.1);
+
+    for (idx, scenario) in scenarios.iter().enumerate() {
+        let Some(summary) = scenario.instance.summary() else {
+            continue;
         };
+        let line = ScenarioLine::new(idx);
+        let text_style = ("sans-serif", 16)
+            .into_font()
+            .color(&line.shaded(BLACK))
+            .pos(Pos::new(HPos::Left, VPos::Top));
 
-        chart
-            .draw_series(LineSeries::new(inst.iter_my(idx, true), &colors[idx]))?
-            .label(labels[idx])
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style.to_owned()));
+        let extents = bed_extents(scenario.instance);
+        for (bed_idx, ((x_in, x_out), bed)) in extents.into_iter().zip(&summary.beds).enumerate() {
+            if x_in >= x_range.end || x_out <= x_range.start {
+                continue;
+            }
+
+            if idx == 0 && bed_idx % 2 == 1 {
+                chart.draw_series(std::iter::once(Rectangle::new(
+                    [
+                        (x_in.max(x_range.start), y_range.start),
+                        (x_out.min(x_range.end), y_range.end),
+                    ],
+                    BLACK.mix(0.06).filled(),
+                )))?;
+            }
+            if x_out < x_range.end {
+                let boundary = vec![(x_out, y_range.start), (x_out, y_range.end)];
+                chart.draw_series(line.elements(boundary, BLACK.mix(0.4), scale))?;
+            }
+
+            let mut lines = vec![
+                format!("Bed {}", bed.bed),
+                format!("L {:.3}", bed.length),
+                format!("NH3 {:.4}", bed.nh3_out),
+            ];
+            if scenarios.len() > 1 {
+                lines[0] = format!("Bed {} {}", bed.bed, scenario.label);
+            }
+            let anchor = (x_in.max(x_range.start) + margin.0, y_range.end - margin.1);
+            let first_row = idx * (BED_ANNOTATION_ROWS + 1);
+            chart.draw_series(lines.into_iter().enumerate().map(|(row, line)| {
+                let offset = (0, 18 * (first_row + row) as i32);
+                EmptyElement::at(anchor) + Text::new(line, offset, text_style.clone())
+            }))?;
+        }
     }
+    Ok(())
+}
+
+/// Writes the difference plot of `other` to `base`, the format is given by `settings` or the
+/// extension.
+#[cfg(not(target_arch="wasm32"))]
+pub fn draw_difference(
+    filename: &str,
+    base: &Scenario,
+    other: &Scenario,
+    settings: &PlotSettings,
+    style: &PlotStyle,
+) -> Result<(), Box<dyn Error>> {
+    render(filename, settings, &Difference { base, other, style })
+}
+
+/// Number of grid points of the difference plots.
+const DIFFERENCE_STEPS: usize = 500;
 
-    let style = ShapeStyle {
-        color: BLACK.to_rgba(),
-        filled: false,
-        stroke_width: 1,
+/// Draws the differences of the NH3 fraction and the temperature of `other` to `base` along the
+/// reactor on any plotters backend.
+///
+/// Only the length axis of `style` is used, the difference axes are symmetric around zero.
+/// Returns the conversions from backend to chart coordinates of the NH3 and the temperature axis.
+#[allow(clippy::type_complexity)]
+pub fn draw_difference_on<DB: DrawingBackend>(
+    draw_area: &DrawingArea<DB, Shift>,
+    base: &Scenario,
+    other: &Scenario,
+    style: &PlotStyle,
+) -> Result<
+    (
+        impl Fn((i32, i32)) -> Option<(f32, f32)>,
+        impl Fn((i32, i32)) -> Option<(f32, f32)>,
+    ),
+    Box<dyn Error>,
+>
+where
+    DB::ErrorType: 'static,
+{
+    check_scenarios(&[base.clone(), other.clone()])?;
+    draw_area.fill(&WHITE)?;
+
+    let diff = other
+        .instance
+        .profile_difference(base.instance, DIFFERENCE_STEPS)
+        .ok_or("Scenarios without results")?;
+    let x_range = match &style.ranges {
+        Some((x_range, _, _)) => x_range.clone(),
+        None => diff.length[0] as f32..diff.length[diff.length.len() - 1] as f32,
+    };
+    // zero sits at the same height on both axes
+    let symmetric = |values: &[f64], min: f64| {
+        let max = values.iter().map(|v| v.abs()).fold(min, f64::max) as f32;
+        -max..max
     };
 
+    let mut chart = prepare_dual_chart(
+        draw_area,
+        &format!("Haber-Bosch Difference {} - {}", other.label, base.label),
+        ("Length Indicator", "Δ Ammonia as partial Fraction"),
+        x_range.clone(),
+        symmetric(&diff.nh3, 1e-4),
+        symmetric(&diff.temperature, 0.1),
+        "Δ Temperature [K]",
+    );
+
+    chart.draw_series(LineSeries::new(
+        [(x_range.start, 0.), (x_range.end, 0.)],
+        BLACK.mix(0.4),
+    ))?;
+    chart
+        .draw_series(LineSeries::new(diff.nh3_points(), GREEN))?
+        .label("Δ Ammonia")
+        .legend(legend_line(GREEN));
     chart
-        .draw_secondary_series(LineSeries::new(
-            inst.iter_my(5, false).map(|(x, t)| (x, t)),
-            &BLACK,
-        ))?
-        .label("Temperature [C°]")
-        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style.to_owned()));
+        .draw_secondary_series(LineSeries::new(diff.temperature_points(), BLACK))?
+        .label("Δ Temperature [K]")
+        .legend(legend_line(BLACK));
 
     chart
         .configure_series_labels()
-        .border_style(&BLACK)
-        .background_style(&GREY_100)
+        .border_style(BLACK)
+        .background_style(GREY_100)
         .position(SeriesLabelPosition::UpperRight)
         .label_font(("sans-serif", 24).into_font())
         .draw()?;
 
+    Ok(chart.into_coord_trans_pair())
+}
+
+/// The renderers need at least one simulated scenario.
+fn check_scenarios(scenarios: &[Scenario]) -> Result<(), Box<dyn Error>> {
+    if scenarios.is_empty() {
+        return Err("Nothing to draw, no scenario given".into());
+    }
+    if let Some(s) = scenarios.iter().find(|s| s.instance.num_results() == 0) {
+        return Err(format!("Scenario {} has not been simulated", s.label).into());
+    }
     Ok(())
 }
 
+/// The catalyst is part of the caption if there is only one scenario.
+fn caption(title: &str, scenarios: &[Scenario]) -> String {
+    match scenarios {
+        [s] => format!("{} with {}", title, s.instance.cat()),
+        _ => title.to_owned(),
+    }
+}
 
-fn raise_range(orig: Range<f32>, percent: f32) -> Range<f32> {
-    let range = (orig.end - orig.start) / 2.;
-    let mid = orig.start + range;
-    (mid - range * percent)..(mid + range * percent)
+fn comb_ranges(r1: Range<f32>, r2: Range<f32>) -> Range<f32> {
+    r1.start.min(r2.start)..r1.end.max(r2.end)
 }
 
-pub fn prepare_chart<'a, DB: DrawingBackend>(
-    draw_area: &'a DrawingArea<DB, Shift>,
-    caption: &str,
-    label_desc: (&str, &str),
-    x_range: Range<f32>,
-    y_range: Range<f32>,
-    with_mesh: bool,
-) -> ChartContext<'a, DB, Cartesian2d<RangedCoordf32, RangedCoordf32>> {
-    let font_caption: FontDesc<'_> = ("sans-serif", 32).into_font();
+fn range_len(range: &Range<f32>) -> f32 {
+    range.end - range.start
+}
 
-    let mut chart = ChartBuilder::on(draw_area)
-        .caption(caption, font_caption)
-        .set_left_and_bottom_label_area_size(40)
-        .set_label_area_size(LabelAreaPosition::Right, 40)
-        .margin(12)
-        .build_cartesian_2d(
-            raise_range(x_range, 1.05f32),
-            raise_range(y_range, 1.05f32))
-            .unwrap();
+/// The axis ranges of `style` or the ranges that cover the results of all scenarios.
+fn axis_ranges(scenarios: &[Scenario], style: &PlotStyle) -> AxisRanges {
+    if let Some(ranges) = &style.ranges {
+        return ranges.clone();
+    }
 
-    if with_mesh {
-        chart
-            .configure_mesh()
-            .x_desc(label_desc.0)
-            .y_desc(label_desc.1)
-            .draw()
-            .unwrap();
+    scenarios
+        .iter()
+        .map(|s| {
+            (
+                s.instance.get_x_range(),
+                s.instance.get_concentration_range(),
+                s.instance.get_temperature_range(),
+            )
+        })
+        .reduce(|(x1, c1, t1), (x2, c2, t2)| {
+            (comb_ranges(x1, x2), comb_ranges(c1, c2), comb_ranges(t1, t2))
+        })
+        .unwrap_or((0f32..1f32, 0f32..1f32, 0f32..1f32))
+}
+
+fn legend_line(color: RGBColor) -> impl Fn((i32, i32)) -> PathElement<(i32, i32)> {
+    move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color)
+}
+
+/// A simulated instance with the label it gets in the legends.
+#[derive(Debug, Clone)]
+pub struct Scenario<'a> {
+    pub instance: &'a HaberBoschInstance,
+    pub label: String,
+}
+
+impl<'a> Scenario<'a> {
+    pub fn new(instance: &'a HaberBoschInstance, label: impl Into<String>) -> Self {
+        Scenario {
+            instance,
+            label: label.into(),
+        }
     }
-    chart
 }
 
-/*
-// IDEA for not exposing life times: USE Closures, e.g.:
-// fn build_chart(&'a self, build_fn: Fn, adapt_chart_fn: Fn) -> Result<ChartContext<'a, ...>, Error>
-draw_area.build_chart(|builder| {
-        builder
-            .caption("Title")
-            .set_left_and_bottom_label_area_size(40)
-            .build_cartesian_2d(0f32..10f32, 0f32..100f32)?;
-    }, |chart| {
-        chart.configure.mesh()
-            .x_desc("Magic Numbers")
-            .y_desc("Magic Heights")
-            .draw()?;
-    })
-*/
-
-pub fn prepare_dual_chart<'a, DB: DrawingBackend>(
-    draw_area: &'a DrawingArea<DB, Shift>,
-    caption: &str,
-    label_desc: (&str, &str),
-    x_range: Range<f32>,
-    y_range: Range<f32>,
-    y2_range: Range<f32>,
-    ylabel2: &str,
-) -> DualCoordChartContext<
-    'a,
-    DB,
-    Cartesian2d<RangedCoordf32, RangedCoordf32>,
-    Cartesian2d<RangedCoordf32, RangedCoordf32>,
-> {
-    let mut chart = prepare_chart(
-        draw_area,
-        caption,
-        label_desc,
-        x_range.clone(),
-        y_range,
-        false,
-    )
-    .set_secondary_coord(
-        raise_range(x_range, 1.05f32),
-        raise_range(y2_range, 1.05f32),
-    );
+/// Dash patterns of the scenarios, alternating lengths of the dashes and the gaps relative to the
+/// axis ranges. An empty pattern is a solid line.
+const LINE_PATTERNS: [&[f32]; 4] = [
+    &[],
+    &[0.012, 0.008],
+    &[0.002, 0.006],
+    &[0.012, 0.006, 0.002, 0.006],
+];
 
-    chart
-        .configure_mesh()
-        .disable_x_mesh()
-        .disable_y_mesh()
-        .x_desc(label_desc.0)
-        .y_desc(label_desc.1)
-        .draw()
-        .unwrap();
+/// [px] length of one axis in the legend samples of the patterns.
+const LEGEND_PATTERN_SCALE: f32 = 1000.;
 
-    chart
-        .configure_secondary_axes()
-        .y_desc(ylabel2)
-        .draw()
-        .unwrap();
+/// Colours of the scenarios in plots with one line per scenario, the Okabe-Ito palette that
+/// stays distinguishable with colour blindness.
+const SCENARIO_COLORS: [RGBColor; 7] = [
+    BLACK,
+    RGBColor(0, 114, 178),
+    RGBColor(213, 94, 0),
+    RGBColor(0, 158, 115),
+    RGBColor(204, 121, 167),
+    RGBColor(230, 159, 0),
+    RGBColor(86, 180, 233),
+];
 
-    chart
+/// Line pattern and colours of the scenario at a position of the list.
+///
+/// The patterns are used up first, after that they repeat with darker colours.
+#[derive(Debug, Copy, Clone)]
+struct ScenarioLine {
+    pattern: &'static [f32],
+    /// number of times the patterns have been used up
+    round: usize,
+    /// of the scenario in plots with one line per scenario
+    color: RGBColor,
+}
+
+impl ScenarioLine {
+    fn new(idx: usize) -> Self {
+        ScenarioLine {
+            pattern: LINE_PATTERNS[idx % LINE_PATTERNS.len()],
+            round: idx / LINE_PATTERNS.len(),
+            color: SCENARIO_COLORS[idx % SCENARIO_COLORS.len()],
+        }
+    }
+
+    /// Darkens `color` for each round of patterns, black turns grey instead.
+    fn shaded(&self, color: RGBColor) -> RGBColor {
+        if self.round == 0 {
+            return color;
+        }
+        if color == BLACK {
+            let v = (48 * self.round).min(160) as u8;
+            return RGBColor(v, v, v);
+        }
+        let factor = 0.6f64.powi(self.round as i32);
+        let shade = |c: u8| (c as f64 * factor) as u8;
+        RGBColor(shade(color.0), shade(color.1), shade(color.2))
+    }
+
+    /// The line through `points` split into the dashes of the pattern.
+    ///
+    /// `scale` are the lengths of the axes, so the dashes look the same on all charts.
+    fn dashes(&self, points: Vec<(f32, f32)>, scale: (f32, f32)) -> Vec<Vec<(f32, f32)>> {
+        if self.pattern.is_empty() {
+            vec![points]
+        } else {
+            split_dashes(&points, self.pattern, scale)
+        }
+    }
+
+    fn elements<S: Into<ShapeStyle> + Copy>(
+        &self,
+        points: Vec<(f32, f32)>,
+        style: S,
+        scale: (f32, f32),
+    ) -> impl Iterator<Item = PathElement<(f32, f32)>> {
+        self.dashes(points, scale)
+            .into_iter()
+            .map(move |dash| PathElement::new(dash, style))
+    }
+
+    /// The dashes of a 20 pixel long sample of the pattern, dashes shorter than a pixel are
+    /// left out.
+    fn legend_dashes(&self) -> Vec<Vec<BackendCoord>> {
+        let scale = (LEGEND_PATTERN_SCALE, LEGEND_PATTERN_SCALE);
+        self.dashes(vec![(0., 0.), (20., 0.)], scale)
+            .into_iter()
+            .map(|dash| {
+                dash.into_iter()
+                    .map(|(x, y)| (x.round() as i32, y.round() as i32))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|dash| dash.first() != dash.last())
+            .collect()
+    }
+
+    /// The legend entry, the sample of the pattern drawn as one element.
+    fn legend<'b, DB: DrawingBackend + 'b>(
+        &self,
+        color: RGBColor,
+        (x, y): (i32, i32),
+    ) -> impl IntoDynElement<'b, DB, (i32, i32)> {
+        let sample = DashedPath {
+            dashes: self.legend_dashes(),
+            style: color.into(),
+        };
+        EmptyElement::at((x, y)) + sample
+    }
+}
+
+/// Separate dashes drawn as one element, plotters has no dashed path element yet.
+struct DashedPath {
+    dashes: Vec<Vec<BackendCoord>>,
+    style: ShapeStyle,
+}
+
+impl<'a> PointCollection<'a, BackendCoord> for &'a DashedPath {
+    type Point = &'a BackendCoord;
+    type IntoIter = std::iter::Flatten<std::slice::Iter<'a, Vec<BackendCoord>>>;
+
+    fn point_iter(self) -> Self::IntoIter {
+        self.dashes.iter().flatten()
+    }
+}
+
+impl<DB: DrawingBackend> Drawable<DB> for DashedPath {
+    fn draw<I: Iterator<Item = BackendCoord>>(
+        &self,
+        mut points: I,
+        backend: &mut DB,
+        _: (u32, u32),
+    ) -> Result<(), DrawingErrorKind<DB::ErrorType>> {
+        // the points of all dashes arrive in one iterator
+        for dash in &self.dashes {
+            let dash: Vec<_> = points.by_ref().take(dash.len()).collect();
+            backend.draw_path(dash, &self.style)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the polyline `points` into dashes, `pattern` alternates between the lengths of the
+/// dashes and the gaps in units of the axis lengths given by `scale`.
+fn split_dashes(points: &[(f32, f32)], pattern: &[f32], scale: (f32, f32)) -> Vec<Vec<(f32, f32)>> {
+    let mut dashes = vec![];
+    let mut dash = vec![];
+    // the even entries of the pattern are dashes
+    let (mut idx, mut left) = (0, pattern[0]);
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let len = (((b.0 - a.0) / scale.0).powi(2) + ((b.1 - a.1) / scale.1).powi(2)).sqrt();
+        if !(len.is_finite() && len > 0.) {
+            continue;
+        }
+
+        if idx % 2 == 0 && dash.is_empty() {
+            dash.push(a);
+        }
+        let mut pos = 0.;
+        while len - pos > left {
+            pos += left;
+            let t = pos / len;
+            dash.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+            if idx % 2 == 0 {
+                dashes.push(std::mem::take(&mut dash));
+            }
+            idx = (idx + 1) % pattern.len();
+            left = pattern[idx];
+        }
+        left -= len - pos;
+        if idx % 2 == 0 {
+            dash.push(b);
+        }
+    }
+    if dash.len() > 1 {
+        dashes.push(dash);
+    }
+    dashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::simulated_kmir;
+
+    /// The position and the content of the text elements of an SVG file.
+    fn svg_texts(svg: &str) -> Vec<((i32, i32), String)> {
+        let attribute = |element: &str, name: &str| {
+            let start = element.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+            let len = element[start..].find('"').unwrap();
+            element[start..start + len].parse().unwrap()
+        };
+        svg.split("<text")
+            .skip(1)
+            .map(|element| {
+                let start = element.find('>').unwrap() + 1;
+                let end = element.find("</text>").unwrap();
+                let pos = (attribute(element, "x"), attribute(element, "y"));
+                (pos, element[start..end].trim().to_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bed_extents_follow_the_results() {
+        let inst = simulated_kmir(2);
+        let extents = bed_extents(&inst);
+        assert_eq!(extents.len(), 2);
+        assert_eq!(extents[0].0, 0.);
+        // the length continues from one bed into the next
+        assert_eq!(extents[0].1, extents[1].0);
+        for (extent, res) in extents.iter().zip(inst.results()) {
+            assert!(extent.0 < extent.1);
+            assert_eq!(extent.1, *res.x_out.last().unwrap() as f32);
+        }
+    }
+
+    #[test]
+    fn marks_and_annotates_the_beds_of_all_scenarios() {
+        let (two, one) = (simulated_kmir(2), simulated_kmir(1));
+        let scenarios = [Scenario::new(&two, "two"), Scenario::new(&one, "one")];
+        let style = PlotStyle::new();
+        let mut svg = String::new();
+        // the pixels of the plot area, the conversion to chart coordinates fails outside of it
+        let (columns, rows, x_range) = {
+            let area = SVGBackend::with_string(&mut svg, (1600, 900)).into_drawing_area();
+            let (to_chart, _) = draw_concentations_on(&area, &scenarios, &style).unwrap();
+            area.present().unwrap();
+            let columns: Vec<_> = (0..1600).filter(|x| to_chart((*x, 450)).is_some()).collect();
+            let rows: Vec<_> = (0..900).filter(|y| to_chart((800, *y)).is_some()).collect();
+            // the chart adds a margin to the axis ranges
+            let x_range = to_chart((columns[0], 450)).unwrap().0
+                ..to_chart((*columns.last().unwrap(), 450)).unwrap().0;
+            (columns, rows, x_range)
+        };
+        let (left, right) = (columns[0], *columns.last().unwrap());
+        let (top, bottom) = (rows[0], *rows.last().unwrap());
+
+        let annotations: Vec<_> = svg_texts(&svg)
+            .into_iter()
+            .filter(|(_, text)| text.starts_with("Bed "))
+            .collect();
+        let labels: Vec<_> = annotations.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(labels, ["Bed 1 two", "Bed 2 two", "Bed 1 one"]);
+
+        let to_column = |x: f32| {
+            left + ((x - x_range.start) / range_len(&x_range) * (right - left) as f32) as i32
+        };
+        let starts = [bed_extents(&two)[0].0, bed_extents(&two)[1].0, bed_extents(&one)[0].0];
+        for (((x, y), text), start) in annotations.iter().zip(starts) {
+            assert!(*y > top && *y < bottom, "{} at row {}", text, y);
+            // just right of the start of the bed
+            let column = to_column(start);
+            assert!(*x > column && *x < column + 16, "{} at column {}", text, x);
+        }
+        // the second scenario is written below the first one
+        assert!(annotations[2].0 .1 > annotations[0].0 .1);
+
+        // the vertical lines at the bed ends, split into the dashes of the patterns
+        let boundaries: Vec<i32> = svg
+            .split("<polyline")
+            .filter(|element| element.contains(r#"opacity="0.4""#))
+            .filter_map(|element| {
+                let start = element.find(r#"points=""#)? + 8;
+                let points: Vec<(i32, i32)> = element[start..]
+                    .split('"')
+                    .next()?
+                    .split_whitespace()
+                    .map(|point| {
+                        let (x, y) = point.split_once(',').unwrap();
+                        (x.parse().unwrap(), y.parse().unwrap())
+                    })
+                    .collect();
+                points.iter().all(|p| p.0 == points[0].0).then_some(points[0].0)
+            })
+            .collect();
+        let ends = [bed_extents(&two)[0].1, bed_extents(&two)[1].1, bed_extents(&one)[0].1];
+        for end in ends {
+            let column = to_column(end);
+            assert!(
+                boundaries.iter().any(|x| (x - column).abs() <= 1),
+                "no line at the bed end {} in {:?}",
+                end,
+                boundaries
+            );
+        }
+    }
+
+    #[test]
+    fn uses_up_the_patterns_before_the_colours() {
+        let lines: Vec<_> = (0..9).map(ScenarioLine::new).collect();
+        for (idx, line) in lines.iter().enumerate() {
+            assert_eq!(line.pattern, LINE_PATTERNS[idx % LINE_PATTERNS.len()]);
+            assert_eq!(line.round, idx / LINE_PATTERNS.len());
+        }
+        // the first round keeps the colours, the later ones darken them
+        assert_eq!(lines[3].shaded(BLUE), BLUE);
+        let reds: Vec<_> = [0, 4, 8].iter().map(|idx| lines[*idx].shaded(RED).0).collect();
+        assert!(reds[0] > reds[1] && reds[1] > reds[2]);
+        assert_eq!(lines[4].shaded(BLACK), RGBColor(48, 48, 48));
+        assert_ne!(lines[4].shaded(BLACK), lines[8].shaded(BLACK));
+    }
+
+    #[test]
+    fn legend_samples_follow_the_patterns() {
+        let samples: Vec<_> = (0..LINE_PATTERNS.len())
+            .map(|idx| ScenarioLine::new(idx).legend_dashes())
+            .collect();
+        assert_eq!(samples[0], vec![vec![(0, 0), (20, 0)]]);
+        assert_eq!(samples[1], vec![vec![(0, 0), (12, 0)]]);
+        assert_eq!(
+            samples[2],
+            vec![vec![(0, 0), (2, 0)], vec![(8, 0), (10, 0)], vec![(16, 0), (18, 0)]]
+        );
+        assert_eq!(samples[3], vec![vec![(0, 0), (12, 0)], vec![(18, 0), (20, 0)]]);
+    }
 }
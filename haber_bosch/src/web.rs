@@ -4,7 +4,10 @@ use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
 use crate::{configuration, simulation, v2_hints::*, web_range, web_visualization};
-use crate::{configuration::Catalyst, console_log};
+use crate::{configuration::Catalyst, configuration::CatalystKinetics, console_log};
+use crate::difference::MetricDelta;
+use crate::summary::SimulationSummary;
+use crate::visualization::{CurveOverlay, PlotStyle, Scenario};
 
 //-------------------------------------------------------------------------------------------------
 // Next Implementatin of WebModelInput and WebInput structures
@@ -13,48 +16,76 @@ use crate::{configuration::Catalyst, console_log};
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone)]
 pub struct WebModelInput {
-    catalyst: Catalyst,
+    /// name of the scenario in the legends, numbered if empty
+    pub label: String,
+    catalyst: CatalystKinetics,
     pub pressure: f64,
     pub num_beds: usize,
     pub beds: Vec<crate::configuration::HaberBoschBedSetup>,
+    pub solver_settings: configuration::SolverSettings,
+    pub feed: configuration::FeedComposition,
 }
 
 #[wasm_bindgen]
 impl WebModelInput {
     pub fn new_kmir() -> Self {
         WebModelInput {
-            catalyst: Catalyst::KMIR,
+            label: String::new(),
+            catalyst: Catalyst::KMIR.kinetics(),
             pressure: KMIR_REACTOR_PRESSURE,
             num_beds: 2,
             beds: vec![],
+            solver_settings: configuration::SolverSettings::default(),
+            feed: configuration::FeedComposition::default(),
         }
     }
 
     pub fn new_fn() -> Self {
         WebModelInput {
-            catalyst: Catalyst::FN,
+            label: String::new(),
+            catalyst: Catalyst::FN.kinetics(),
             pressure: FN_REACTOR_PRESSURE,
             num_beds: 2,
             beds: vec![],
+            solver_settings: configuration::SolverSettings::default(),
+            feed: configuration::FeedComposition::default(),
         }
     }
 
-    pub fn set_catalyst(&mut self, catalyst: &str) {
-        self.catalyst = Catalyst::from_str(catalyst).unwrap_or(Catalyst::KMIR);
+    /// Selects a built-in or registered catalyst by name.
+    pub fn set_catalyst(&mut self, catalyst: &str) -> Result<(), String> {
+        self.catalyst = CatalystKinetics::from_str(catalyst)?;
+        Ok(())
+    }
+
+    pub fn catalyst_name(&self) -> String {
+        self.catalyst.name.clone()
     }
 }
 
+/// Registers a vendor catalyst so it can be selected with `WebModelInput::set_catalyst`.
+#[wasm_bindgen]
+pub fn register_catalyst(kinetics: CatalystKinetics) -> Result<(), String> {
+    kinetics.register().map_err(|e| e.to_string())
+}
+
+/// The names of all catalysts that can be selected.
+#[wasm_bindgen]
+pub fn registered_catalysts() -> Vec<String> {
+    CatalystKinetics::registered()
+}
+
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone)]
 pub struct WebInput {
-    /// input for the main model of the Haber-Bosch process that will get rendered
-    pub main: WebModelInput,
-
-    /// optional secondary input of a Haber-Bosch process model that acts as a diff view
-    pub alt: Option<WebModelInput>,
+    /// the scenarios that get simulated and rendered, the first one is the main scenario
+    pub scenarios: Vec<WebModelInput>,
 
     /// optional settings for the axis of the plot
     pub axis_settings: Option<WebAxisInput>,
+
+    /// curves drawn on top of the temperature over yield plot
+    pub overlay: CurveOverlay,
 }
 
 #[wasm_bindgen]
@@ -64,12 +95,20 @@ impl WebInput {
     }
 }
 
+impl WebInput {
+    fn plot_style(&self) -> PlotStyle {
+        PlotStyle::new()
+            .with_ranges(self.axis_settings.map(|axiss| axiss.to_range_tuple()))
+            .with_overlay(self.overlay)
+    }
+}
+
 impl Default for WebInput {
     fn default() -> Self {
         Self {
-            main: WebModelInput::new_kmir(),
-            alt: Some(WebModelInput::new_fn()),
+            scenarios: vec![WebModelInput::new_kmir(), WebModelInput::new_fn()],
             axis_settings: Some(WebAxisInput::default()),
+            overlay: CurveOverlay::default(),
         }
     }
 }
@@ -81,11 +120,17 @@ impl Default for WebInput {
 /// Type alias for the result of a drawing function.
 pub type DrawResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+/// Type alias for a function that converts screen coordinates to chart coordinates.
+type CoordConverter = Box<dyn Fn((i32, i32)) -> Option<(f64, f64)>>;
+
 /// Type used on the JS side to convert screen coordinates to chart
 /// coordinates.
 #[wasm_bindgen]
 pub struct WebChart {
-    convert: Box<dyn Fn((i32, i32)) -> Option<(f64, f64)>>,
+    convert: CoordConverter,
+    stats: Vec<configuration::BedSolverStats>,
+    summaries: Vec<SimulationSummary>,
+    deltas: Vec<MetricDelta>,
 }
 
 /// Result of screen to chart coordinates conversion.
@@ -102,29 +147,22 @@ impl WebChart {
         input: WebInput,
     ) -> Result<WebChart, JsValue> {
         console_log!("{:?}!", input);
-        let inst = WebChart::simulate(&input.main);
-
-        let inst2 = if let Some(alt) = input.alt {
-            console_log!("Simulate alt");
-            Some(WebChart::simulate(&alt))
-        } else {
-            console_log!("Only one scenario");
-            None
-        };
+        let instances = WebChart::simulate_all(&input)?;
 
-        let axis_override = if let Some(axiss) = input.axis_settings {
-            Some(axiss.to_range_tuple())
-        } else {
-            None
-        };
-
-        let map_coord =
-            web_visualization::draw_concentrations_for_canvas(canvas_id, &inst, inst2, axis_override)
-                .unwrap()
-                .0;
+        let scenarios = WebChart::labelled(&input, &instances);
+        let map_coord = web_visualization::draw_concentrations_for_canvas(
+            canvas_id,
+            &scenarios,
+            &input.plot_style(),
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .0;
 
         Ok(WebChart {
             convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+            stats: instances[0].bed_stats(),
+            summaries: WebChart::summaries_of(&instances),
+            deltas: WebChart::deltas_of(&instances),
         })
     }
 
@@ -132,53 +170,123 @@ impl WebChart {
         canvas_id: &str,
         input: &WebInput,
     ) -> Result<WebChart, JsValue> {
-        let inst = WebChart::simulate(&input.main);
-
-        let inst2 = if let Some(alt) = &input.alt {
-            console_log!("Simulate alt");
-            Some(WebChart::simulate(alt))
-        } else {
-            console_log!("Only one scenario");
-            None
-        };
-
-        let axis_override = if let Some(axiss) = input.axis_settings {
-            Some(axiss.to_range_tuple())
-        } else {
-            None
-        };
+        let instances = WebChart::simulate_all(input)?;
 
+        let scenarios = WebChart::labelled(input, &instances);
         let map_coord = web_visualization::draw_temperature_over_yield_for_canvas(
             canvas_id,
-            &inst,
-            inst2,
-            axis_override,
+            &scenarios,
+            &input.plot_style(),
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        Ok(WebChart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+            stats: instances[0].bed_stats(),
+            summaries: WebChart::summaries_of(&instances),
+            deltas: WebChart::deltas_of(&instances),
+        })
+    }
+
+    /// Draws the differences of the second scenario to the first one along the reactor.
+    pub fn draw_difference(canvas_id: &str, input: &WebInput) -> Result<WebChart, JsValue> {
+        let instances = WebChart::simulate_all(input)?;
+
+        let scenarios = WebChart::labelled(input, &instances);
+        let [base, other, ..] = scenarios.as_slice() else {
+            return Err(JsValue::from_str("The difference needs two scenarios"));
+        };
+        let map_coord = web_visualization::draw_difference_for_canvas(
+            canvas_id,
+            base,
+            other,
+            &input.plot_style(),
         )
-        .unwrap()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
         .0;
 
         Ok(WebChart {
             convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+            stats: instances[0].bed_stats(),
+            summaries: WebChart::summaries_of(&instances),
+            deltas: WebChart::deltas_of(&instances),
         })
     }
 
-    fn simulate(model_input: &WebModelInput) -> configuration::HaberBoschInstance {
+    fn simulate(model_input: &WebModelInput) -> Result<configuration::HaberBoschInstance, JsValue> {
         let mut builder = configuration::HaberBoschInstanceBuilder::create(
             model_input.pressure,
-            model_input.catalyst,
-        );
-        for i in 0..model_input.num_beds {
-            builder = builder.add_bed(
-                model_input.beds[i].t_start,
-                model_input.beds[i].t_slope,
-                model_input.beds[i].t_max,
-                model_input.beds[i].beta,
-            );
+            model_input.catalyst.clone(),
+        )
+        .with_solver_settings(model_input.solver_settings)
+        .with_feed_composition(model_input.feed);
+        for bed in model_input.beds.iter().take(model_input.num_beds) {
+            builder = builder.add_bed_setup(*bed);
+        }
+        let mut inst = builder
+            .build()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        simulation::sequential_simulation(&mut inst, false)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(inst)
+    }
+
+    fn simulate_all(input: &WebInput) -> Result<Vec<configuration::HaberBoschInstance>, JsValue> {
+        if input.scenarios.is_empty() {
+            return Err(JsValue::from_str("At least one scenario is required"));
         }
-        let mut inst = builder.build();
+        console_log!("Simulate {} scenarios", input.scenarios.len());
+        input.scenarios.iter().map(WebChart::simulate).collect()
+    }
+
+    /// Pairs the instances with the labels of their inputs.
+    fn labelled<'a>(
+        input: &WebInput,
+        instances: &'a [configuration::HaberBoschInstance],
+    ) -> Vec<Scenario<'a>> {
+        input
+            .scenarios
+            .iter()
+            .zip(instances)
+            .enumerate()
+            .map(|(idx, (model_input, inst))| {
+                let label = if model_input.label.is_empty() {
+                    format!("Scenario {}", idx + 1)
+                } else {
+                    model_input.label.clone()
+                };
+                Scenario::new(inst, label)
+            })
+            .collect()
+    }
+
+    fn summaries_of(instances: &[configuration::HaberBoschInstance]) -> Vec<SimulationSummary> {
+        instances.iter().filter_map(|i| i.summary()).collect()
+    }
+
+    /// The key figures of the second scenario relative to the first one.
+    fn deltas_of(instances: &[configuration::HaberBoschInstance]) -> Vec<MetricDelta> {
+        match instances {
+            [base, other, ..] => other.metric_deltas(base).unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    /// The summaries of all scenarios in the order of the input.
+    pub fn summaries(&self) -> Vec<SimulationSummary> {
+        self.summaries.clone()
+    }
+
+    /// The key figures of the second scenario relative to the first one, empty for a single
+    /// scenario.
+    pub fn metric_deltas(&self) -> Vec<MetricDelta> {
+        self.deltas.clone()
+    }
 
-        simulation::sequential_simulation(&mut inst, false);
-        inst
+    /// The solver statistics of each bed of the main scenario.
+    pub fn solver_stats(&self) -> Vec<configuration::BedSolverStats> {
+        self.stats.clone()
     }
 
     /// This function can be used to convert screen coordinates to
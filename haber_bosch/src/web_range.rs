@@ -25,7 +25,7 @@ pub struct WebRange {
 
 impl WebRange {
     pub fn clone_with_def(&self, def: usize) -> Self {
-        let mut reval = self.clone();
+        let mut reval = *self;
         reval.def_val = def;
         reval
     }
@@ -7,10 +7,15 @@ const canvas = document.getElementById("canvas");
 const csde = document.getElementById("canvas_sized_disp_el");
 const coord = document.getElementById("coord");
 const status = document.getElementById("status");
+const solver_stats = document.getElementById("solver_stats");
+const results = document.getElementById("results");
+const deltas = document.getElementById("deltas");
 
 const plot_type = document.getElementById("plot_type");
 const auto_ranges = document.getElementById("auto_ranges");
 const diff_tool = document.getElementById("diff_tool")
+const show_equilibrium = document.getElementById("show_equilibrium");
+const show_max_rate = document.getElementById("show_max_rate");
 
 const catalyst_lhs = document.getElementById("catalyst_lhs");
 const pressure_lhs = document.getElementById("pressure_lhs");
@@ -30,6 +35,10 @@ const min_temp = document.getElementById("t_min");
 const max_temp = document.getElementById("t_max");
 
 let chart = null;
+/** Labels of the scenarios of the current chart, in the order of the summaries. */
+let labels = [];
+/** Scenarios pinned for comparison, stored as plain values of `read_scenario`. */
+let pinned = [];
 
 /** Main entry point */
 export function main() {
@@ -61,6 +70,18 @@ function setupUI() {
 		toggle_visibility(diff_tool);
 		updateHBPlot();
 	})
+	document.getElementById("btn_pin").addEventListener("click", function() {
+		let scenario = read_scenario(false);
+		scenario.label = `Pinned ${pinned.length + 1}`;
+		pinned.push(scenario);
+		updateHBPlot();
+	});
+	document.getElementById("btn_clear_pinned").addEventListener("click", function() {
+		pinned = [];
+		updateHBPlot();
+	});
+	show_equilibrium.addEventListener("change", updateHBPlot);
+	show_max_rate.addEventListener("change", updateHBPlot);
 
 	let postfix = ["lhs", "rhs"];	
 
@@ -298,61 +319,62 @@ function setup_bed_events(idx, alt) {
 	}
 }
 
-function read_bed(idx, alt) {
-	let [pre, post] = bed_info(idx, alt);
+/** Reads the controls of the main or the alternative scenario as plain values. */
+function read_scenario(alt) {
+	let catalyst = alt ? catalyst_rhs : catalyst_lhs;
+	let pressure = alt ? pressure_rhs : pressure_lhs;
+	let num_beds = alt ? num_beds_rhs : num_beds_lhs;
 
-	let cat_name = null;
-	if (alt) {
-		cat_name = "FN";
-	} else {
-		cat_name = "KMIR";
+	let start_temps = [];
+	for(let i=0; i<Number(num_beds.value); ++i) {
+		let [pre, post] = bed_info(i+1, alt);
+		start_temps.push(Number(document.getElementById(pre + "start_temp" + post).value));
 	}
+	return {
+		label: alt ? "Alternative" : "Main",
+		alt: alt,
+		catalyst: catalyst.selectedOptions[0].value,
+		pressure: Number(pressure.value),
+		start_temps: start_temps,
+	};
+}
 
-	let bed = HaberBoschBedSetup.from_constants(idx-1, cat_name);
-    bed.t_start = Number(document.getElementById(pre + "start_temp" + post).value) + 273;
-    //bed.t_slope = Number(document.getElementById(pre + "slope_temp" + post).value);
-	if(alt) {
-		bed.t_slope = 20;
-	} else {
+/** Fills the model input `wip` with the plain values of a scenario. */
+function to_model_input(wip, scenario) {
+	wip.label = scenario.label;
+	wip.set_catalyst(scenario.catalyst);
+	wip.pressure = scenario.pressure;
+	wip.num_beds = scenario.start_temps.length;
+
+	// the bed constants follow the column the scenario was read from
+	let cat_name = scenario.alt ? "FN" : "KMIR";
+	let beds = wip.beds;
+	for(let i=0; i<scenario.start_temps.length; ++i) {
+		let bed = HaberBoschBedSetup.from_constants(i, cat_name);
+		bed.t_start = scenario.start_temps[i] + 273;
 		bed.t_slope = 20;
+		beds.push(bed);
 	}
-	return bed;
+	wip.beds = beds;
+	return wip;
 }
 
 function read_input() {
 	let input = WebInput.new();
 
-	let selected = catalyst_lhs.selectedOptions[0];
-	let wip_main = input.main;
-	wip_main.set_catalyst(selected.value);
-	
-	wip_main.pressure = Number(pressure_lhs.value);
-	wip_main.num_beds = Number(num_beds_lhs.value);
-
-	let tmp_beds = wip_main.beds;
-	for(let i=0; i<wip_main.num_beds; ++i) {
-		tmp_beds.push(read_bed(i+1, false));
-	}
-	wip_main.beds = tmp_beds;
-	input.main = wip_main;
-
+	let scenarios = [read_scenario(false)];
 	if (diff_tool.checked) {
-		let wip_alt = input.alt;
-		let alt_selected = catalyst_rhs.selectedOptions[0];
-		wip_alt.set_catalyst(alt_selected.value);
-
-		wip_alt.pressure = Number(pressure_rhs.value);
-		wip_alt.num_beds = Number(num_beds_rhs.value);
+		scenarios.push(read_scenario(true));
+	}
+	scenarios = scenarios.concat(pinned);
 
-		let alt_tmp_beds = wip_alt.beds;
-		for(let k=0; k<wip_alt.num_beds; ++k) {
-			alt_tmp_beds.push(read_bed(k+1, true));
-		}
-		wip_alt.beds = alt_tmp_beds;
-		input.alt = wip_alt;
-	} else {
-		input.alt = null;
+	// every read of `input.scenarios` hands out fresh copies of the defaults
+	let models = [];
+	for (const scenario of scenarios) {
+		models.push(to_model_input(input.scenarios[0], scenario));
 	}
+	input.scenarios = models;
+	labels = scenarios.map(scenario => scenario.label);
 
 	if(!auto_ranges.checked) {
 		let wip = input.axis_settings;
@@ -364,6 +386,11 @@ function read_input() {
 	} else {
 		input.axis_settings = null;
 	}
+
+	let overlay = input.overlay;
+	overlay.equilibrium = show_equilibrium.checked;
+	overlay.max_rate = show_max_rate.checked;
+	input.overlay = overlay;
 	return input;
 }
 
@@ -381,6 +408,13 @@ function updateHBPlot() {
 		case "toy":
 			chart = WebChart.draw_temperature_over_yield("canvas", input);		
 			break;
+		case "dif":
+			if (labels.length < 2) {
+				status.innerText = "Status: The difference needs the diff tool or a pinned scenario";
+				return;
+			}
+			chart = WebChart.draw_difference("canvas", input);
+			break;
 		default:
 			status.innerText = `Status: Simulation and Rendering done in ${Math.ceil(end - start)}ms`;
 			return;
@@ -388,4 +422,51 @@ function updateHBPlot() {
 
 	const end = performance.now();
     status.innerText = `Status: Simulation and Rendering done in ${Math.ceil(end - start)}ms`;
-}
\ No newline at end of file
+	show_solver_stats();
+	show_results();
+	show_deltas();
+}
+
+function show_solver_stats() {
+	let stats = chart.solver_stats();
+	let text = "Solver:";
+	for (let i=0; i<stats.length; ++i) {
+		let st = stats[i];
+		text += ` Bed ${i+1}: ${st.num_eval} evals, ${st.accepted_steps}/${st.rejected_steps} steps, ${(st.wall_time*1000).toFixed(2)}ms;`;
+	}
+	solver_stats.innerText = text;
+}
+
+function show_results() {
+	let summaries = chart.summaries();
+	let html = "<tr><th>Scenario</th><th>Bed</th><th>Length</th><th>T_in [°C]</th><th>T_out [°C]</th>"
+		+ "<th>NH3_in</th><th>NH3_out</th><th>X_N2</th><th>X_H2</th></tr>";
+	for (let i=0; i<summaries.length; ++i) {
+		let sum = summaries[i];
+		let scenario = labels[i];
+		for (const bed of sum.beds) {
+			html += `<tr><td>${scenario}</td><td>${bed.bed}</td><td>${bed.length.toFixed(3)}</td>`
+				+ `<td>${bed.t_in.toFixed(1)}</td><td>${bed.t_out.toFixed(1)}</td>`
+				+ `<td>${bed.nh3_in.toFixed(4)}</td><td>${bed.nh3_out.toFixed(4)}</td>`
+				+ `<td>${bed.conversion_n2.toFixed(4)}</td><td>${bed.conversion_h2.toFixed(4)}</td></tr>`;
+		}
+		html += `<tr><td>${scenario}</td><td colspan="8">${sum.catalyst}, length ${sum.total_length.toFixed(3)}, `
+			+ `final yield ${sum.final_yield.toFixed(4)}, outlet pressure ${sum.outlet_pressure.toFixed(2)} bar</td></tr>`;
+	}
+	results.innerHTML = html;
+}
+
+function show_deltas() {
+	let rows = chart.metric_deltas();
+	if (rows.length == 0) {
+		deltas.innerHTML = "";
+		return;
+	}
+
+	let html = `<tr><th>Key Figure</th><th>${labels[0]}</th><th>${labels[1]}</th><th>Δ</th></tr>`;
+	for (const row of rows) {
+		html += `<tr><td>${row.name}</td><td>${row.base.toFixed(4)}</td><td>${row.other.toFixed(4)}</td>`
+			+ `<td>${row.delta >= 0 ? "+" : ""}${row.delta.toFixed(4)}</td></tr>`;
+	}
+	deltas.innerHTML = html;
+}